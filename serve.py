@@ -0,0 +1,16 @@
+# Serves the web demo with the cross-origin isolation headers, which the threads build needs to
+# use SharedArrayBuffer (see worker.js). Usage: python serve.py [port]
+import sys
+from http.server import SimpleHTTPRequestHandler, ThreadingHTTPServer
+
+
+class Handler(SimpleHTTPRequestHandler):
+    def end_headers(self):
+        self.send_header("Cross-Origin-Opener-Policy", "same-origin")
+        self.send_header("Cross-Origin-Embedder-Policy", "require-corp")
+        super().end_headers()
+
+
+port = int(sys.argv[1]) if len(sys.argv) > 1 else 8080
+print(f"serving at http://127.0.0.1:{port}/index.html")
+ThreadingHTTPServer(("127.0.0.1", port), Handler).serve_forever()
@@ -0,0 +1,1843 @@
+//! This file contains the native API, generic over the folding scheme (see `backend.rs`): params
+//! generation, folding, extension and verification of the IVC proofs.
+use ark_bn254::{Fr, G1Projective as G1};
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_ec::{
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    AffineRepr, CurveGroup,
+};
+use ark_ff::{BigInteger, PrimeField};
+use ark_grumpkin::Projective as G2;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::Zero;
+use blake2::{Blake2s256, Digest};
+use rand::{rngs::OsRng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use rand_core::CryptoRngCore;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "parallel")]
+use std::sync::atomic::AtomicUsize;
+#[cfg(feature = "prover")]
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use arkeddsa::ed_on_bn254_twist::{constraints::EdwardsVar, EdwardsProjective};
+use arkeddsa::PublicKey;
+
+use folding_schemes::{
+    commitment::pedersen, frontend::FCircuit, transcript::poseidon::poseidon_canonical_config,
+    FoldingScheme,
+};
+
+use crate::backend::Backend;
+use crate::error::EthDosError;
+use crate::fcircuit::{EthDosCircuit, EthDosParams, EthDosStep};
+use crate::format::{self, Kind, Scheme};
+use crate::keys::{self, SecretKey};
+use crate::revocation::RevocationTree;
+use crate::signature::{
+    self, domain_tag, validate_chain, SigPk, Validity, DEFAULT_CONTEXT, NO_EXPIRY,
+};
+use crate::utils::{dbg, elapsed, get_time};
+
+// define type aliases for the FCircuit (FC) and the params & proofs of the FoldingScheme, to avoid
+// writing the whole type each time.
+pub type FC = EthDosCircuit<Fr, EdwardsProjective, EdwardsVar>;
+pub type ProverParams<B> = <B as FoldingScheme<G1, G2, FC>>::ProverParam;
+pub type VerifierParams<B> = <B as FoldingScheme<G1, G2, FC>>::VerifierParam;
+pub type IVCProof<B> = <B as FoldingScheme<G1, G2, FC>>::IVCProof;
+
+/// Public statement attested by an IVC proof: `pk_i` is at `degree` degrees of distance from
+/// `pk_0`, through vouches not revoked in `revocation_root` and valid within `validity`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProofState {
+    pub pk_0: PublicKey<EdwardsProjective>,
+    pub pk_i: PublicKey<EdwardsProjective>,
+    pub degree: u64,
+    pub revocation_root: Fr,
+    pub validity: Validity,
+    pub weight: u64,
+    pub labels: u64,
+}
+impl ProofState {
+    // decodes the IVC state, where:
+    // state = [ pk_0, pk_i, i, presented, nullifier, challenge, within_max_degree,
+    //           revocation_root, not_before, not_after, weight, labels]
+    pub(crate) fn from_z(z: &[Fr]) -> Result<Self, EthDosError> {
+        check_state_len(z)?;
+        if !z[5].is_zero() {
+            return Err(EthDosError::Verification(
+                "the proof is a presentation, see `decider::verify_presentation`".to_string(),
+            ));
+        }
+        Ok(Self {
+            pk_0: pk_from_xy(z[0], z[1])?,
+            pk_i: pk_from_xy(z[2], z[3])?,
+            degree: fr_to_u64(z[4])?,
+            revocation_root: z[9],
+            validity: validity_from_z(z)?,
+            weight: fr_to_u64(z[12])?,
+            labels: fr_to_u64(z[13])?,
+        })
+    }
+}
+
+/// Public statement attested by a presentation proof (see `decider::present`): the owner of a key
+/// at most `max_degree` degrees from `pk_0` answered `challenge`, with a per-key `nullifier`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Presentation {
+    pub pk_0: PublicKey<EdwardsProjective>,
+    pub max_degree: u64,
+    pub within_max_degree: bool,
+    pub nullifier: Fr,
+    pub challenge: Fr,
+    pub revocation_root: Fr,
+    pub validity: Validity,
+    pub labels: u64,
+}
+impl Presentation {
+    pub(crate) fn from_z(z: &[Fr]) -> Result<Self, EthDosError> {
+        check_state_len(z)?;
+        if z[5] != Fr::from(1_u32) {
+            return Err(EthDosError::Verification(
+                "the proof is not a presentation".to_string(),
+            ));
+        }
+        let within_max_degree = match fr_to_u64(z[8])? {
+            0 => false,
+            1 => true,
+            _ => {
+                return Err(EthDosError::Verification(
+                    "malformed degree comparison".to_string(),
+                ))
+            }
+        };
+        Ok(Self {
+            pk_0: pk_from_xy(z[0], z[1])?,
+            max_degree: fr_to_u64(z[4])?,
+            within_max_degree,
+            nullifier: z[6],
+            challenge: z[7],
+            revocation_root: z[9],
+            validity: validity_from_z(z)?,
+            labels: fr_to_u64(z[13])?,
+        })
+    }
+}
+
+// the window is not checked to be non-empty, see `check_validity`
+fn validity_from_z(z: &[Fr]) -> Result<Validity, EthDosError> {
+    Ok(Validity {
+        not_before: fr_to_u64(z[10])?,
+        not_after: fr_to_u64(z[11])?,
+    })
+}
+
+fn check_state_len(z: &[Fr]) -> Result<(), EthDosError> {
+    if z.len() != 14 {
+        return Err(EthDosError::Verification(format!(
+            "unexpected state length {}",
+            z.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Initial state of the chains starting at `pk_0`, checked against the revocation tree of root
+/// `revocation_root`: `[pk_0, pk_0, 0, 0, 0, 0, 0, revocation_root, 0, NO_EXPIRY, 0, 0]`
+pub fn initial_state(
+    pk_0: PublicKey<EdwardsProjective>,
+    revocation_root: Fr,
+) -> Result<Vec<Fr>, EthDosError> {
+    let xy = pk_0
+        .0
+        .xy()
+        .ok_or_else(|| EthDosError::Signature("pk_0 is the point at infinity".to_string()))?;
+    let pk0 = vec![xy.0, xy.1];
+    Ok([
+        pk0.clone(),
+        pk0,
+        vec![Fr::zero(); 5],
+        vec![revocation_root, Fr::zero(), Fr::from(NO_EXPIRY)],
+        vec![Fr::zero(); 2],
+    ]
+    .concat())
+}
+
+/// Domain of the vouches of the default context, see `signature::domain_tag`
+pub fn default_domain() -> Fr {
+    domain_tag(DEFAULT_CONTEXT)
+}
+
+/// Params of the circuit that folds the vouches of the given domain
+pub fn circuit_params(domain: Fr) -> EthDosParams<Fr> {
+    EthDosParams {
+        poseidon_config: poseidon_canonical_config::<Fr>(),
+        domain,
+    }
+}
+
+// encoding of the domain in the header of the serialized artefacts
+pub(crate) fn domain_bytes(domain: Fr) -> [u8; 32] {
+    let mut b = [0u8; 32];
+    b.copy_from_slice(&domain.into_bigint().to_bytes_le());
+    b
+}
+
+/// Returns the domain for which the serialized artefact was generated, without checking the rest
+/// of its header.
+pub fn artefact_domain(b: &[u8]) -> Result<Fr, EthDosError> {
+    let domain = format::read_header(b)?.domain;
+    Ok(Fr::from_le_bytes_mod_order(&domain))
+}
+
+/// Deterministic source of randomness for the `_with_rng` variants of the methods, only for tests
+/// and benchmarks (for shared params, see `gen_canonical_params`).
+pub fn seeded_rng(seed: u64) -> ChaCha20Rng {
+    ChaCha20Rng::seed_from_u64(seed)
+}
+
+/// Generates the params of the folding scheme for the vouches of the given domain. The params of
+/// different domains are not interchangeable.
+#[cfg(feature = "prover")]
+pub fn gen_params<B: Backend>(
+    domain: Fr,
+) -> Result<(ProverParams<B>, VerifierParams<B>), EthDosError> {
+    gen_params_with_rng::<B, _>(&mut OsRng, domain)
+}
+
+/// Same as `gen_params`, with the given source of randomness (see `seeded_rng`).
+#[cfg(feature = "prover")]
+pub fn gen_params_with_rng<B: Backend, R: CryptoRngCore>(
+    rng: &mut R,
+    domain: Fr,
+) -> Result<(ProverParams<B>, VerifierParams<B>), EthDosError> {
+    let poseidon_config = poseidon_canonical_config::<Fr>();
+
+    let f_circuit = FC::new(circuit_params(domain))?;
+
+    let preprocess_params = B::preprocessor_param(poseidon_config, f_circuit);
+    let start = get_time();
+    let fs_params = B::preprocess(rng, &preprocess_params)?;
+    dbg(format!(
+        "{:?} params generated: {:?}ms",
+        B::SCHEME,
+        elapsed(start)
+    ));
+
+    Ok(fs_params)
+}
+
+/// Generates the canonical params of the given domain, whose Pedersen generators are derived by
+/// hash-to-curve from a seed of the circuit config, so every process gets the same params.
+#[cfg(feature = "prover")]
+pub fn gen_canonical_params<B: Backend>(
+    domain: Fr,
+) -> Result<(ProverParams<B>, VerifierParams<B>), EthDosError> {
+    let seed = format::canonical_seed(circuit_digest()?, B::SCHEME, domain_bytes(domain));
+    // the preprocess fixes the number of generators, which are then replaced
+    let (mut pp, _) = gen_params_with_rng::<B, _>(&mut ChaCha20Rng::from_seed(seed), domain)?;
+    let (cs_params, cf_cs_params) = B::pp_commitment_params_mut(&mut pp);
+    let (h, generators) =
+        hash_to_generators::<<G1 as CurveGroup>::Config>(seed, b"cs", cs_params.generators.len());
+    *cs_params = pedersen::Params { h, generators };
+    let (h, generators) = hash_to_generators::<<G2 as CurveGroup>::Config>(
+        seed,
+        b"cf_cs",
+        cf_cs_params.generators.len(),
+    );
+    *cf_cs_params = pedersen::Params { h, generators };
+    let vp = B::verifier_params(circuit_params(domain), &pp)?;
+    Ok((pp, vp))
+}
+
+// `len` generators and the blinding generator `h` of the Pedersen commitments of the given label,
+// derived from the seed by try-and-increment: the x coordinate is hashed with a counter until it
+// is the one of a point of the curve.
+#[cfg(feature = "prover")]
+fn hash_to_generators<P: SWCurveConfig>(
+    seed: [u8; 32],
+    label: &[u8],
+    len: usize,
+) -> (Projective<P>, Vec<Affine<P>>)
+where
+    P::BaseField: PrimeField,
+{
+    let hash_to_curve = |index: u64| {
+        (0_u64..)
+            .find_map(|counter| {
+                let mut h = Blake2s256::new();
+                h.update(b"ethdos-fold/generators/");
+                h.update(seed);
+                h.update(label);
+                h.update(index.to_le_bytes());
+                h.update(counter.to_le_bytes());
+                let x = P::BaseField::from_le_bytes_mod_order(&h.finalize());
+                Affine::<P>::get_point_from_x_unchecked(x, false)
+                    .map(|p| p.clear_cofactor())
+                    .filter(|p| !p.is_zero())
+            })
+            .unwrap_or_default()
+    };
+    let h = hash_to_curve(u64::MAX).into_group();
+    (h, (0..len as u64).map(hash_to_curve).collect())
+}
+
+/// Derives the verifier params from the prover params of the given domain.
+#[cfg(feature = "prover")]
+pub fn verifier_params_from_prover_params<B: Backend>(
+    pp: &ProverParams<B>,
+    domain: Fr,
+) -> Result<VerifierParams<B>, EthDosError> {
+    B::verifier_params(circuit_params(domain), pp)
+}
+
+/// Digest of the circuit configuration, included in the header of the serialized artefacts. It
+/// does not depend on the domain, which is included separately in the header.
+pub fn circuit_digest() -> Result<[u8; 32], EthDosError> {
+    let params = circuit_params(default_domain());
+    let poseidon_config = params.poseidon_config.clone();
+    let f_circuit = FC::new(params)?;
+    format::circuit_digest(&poseidon_config, f_circuit.state_len())
+}
+
+/// Identifier of the prover params of the given domain, the same as the one of their verifier
+/// params (see `format::params_id`).
+#[cfg(feature = "prover")]
+pub fn prover_params_id<B: Backend>(
+    pp: &ProverParams<B>,
+    domain: Fr,
+) -> Result<[u8; 32], EthDosError> {
+    params_id::<B>(B::pp_commitment_params(pp), domain)
+}
+
+/// Identifier of the verifier params of the given domain, see `prover_params_id`.
+pub fn verifier_params_id<B: Backend>(
+    vp: &VerifierParams<B>,
+    domain: Fr,
+) -> Result<[u8; 32], EthDosError> {
+    params_id::<B>(B::vp_commitment_params(vp), domain)
+}
+
+fn params_id<B: Backend>(
+    (cs_params, cf_cs_params): (&pedersen::Params<G1>, &pedersen::Params<G2>),
+    domain: Fr,
+) -> Result<[u8; 32], EthDosError> {
+    let mut b = vec![];
+    cs_params.serialize_compressed(&mut b)?;
+    cf_cs_params.serialize_compressed(&mut b)?;
+    Ok(format::params_id(
+        circuit_digest()?,
+        B::SCHEME,
+        domain_bytes(domain),
+        &b,
+    ))
+}
+
+#[cfg(feature = "prover")]
+pub fn serialize_prover_params<B: Backend>(
+    pp: &ProverParams<B>,
+    domain: Fr,
+) -> Result<Vec<u8>, EthDosError> {
+    let start = get_time();
+    let mut prover_params_serialized = vec![];
+    pp.serialize_compressed(&mut prover_params_serialized)?;
+    dbg(format!(
+        "{:?} prover params serialized: {:?}ms",
+        B::SCHEME,
+        elapsed(start)
+    ));
+    dbg(format!(
+        "prover_params size: {} mb",
+        prover_params_serialized.len() / (1024 * 1024)
+    ));
+    Ok(format::seal(
+        Kind::ProverParams,
+        B::SCHEME,
+        circuit_digest()?,
+        domain_bytes(domain),
+        prover_params_id::<B>(pp, domain)?,
+        false,
+        &prover_params_serialized,
+    ))
+}
+
+pub fn serialize_verifier_params<B: Backend>(
+    vp: &VerifierParams<B>,
+    domain: Fr,
+) -> Result<Vec<u8>, EthDosError> {
+    let start = get_time();
+    let mut verifier_params_serialized = vec![];
+    vp.serialize_compressed(&mut verifier_params_serialized)?;
+    dbg(format!(
+        "{:?} verifier params serialized: {:?}ms",
+        B::SCHEME,
+        elapsed(start)
+    ));
+    dbg(format!(
+        "verifier_params size: {} mb",
+        verifier_params_serialized.len() / (1024 * 1024)
+    ));
+    Ok(format::seal(
+        Kind::VerifierParams,
+        B::SCHEME,
+        circuit_digest()?,
+        domain_bytes(domain),
+        verifier_params_id::<B>(vp, domain)?,
+        false,
+        &verifier_params_serialized,
+    ))
+}
+
+#[cfg(feature = "prover")]
+pub fn deserialize_prover_params<B: Backend>(
+    b: &[u8],
+    domain: Fr,
+) -> Result<ProverParams<B>, EthDosError> {
+    let b = format::open(
+        Kind::ProverParams,
+        B::SCHEME,
+        circuit_digest()?,
+        domain_bytes(domain),
+        b,
+    )?;
+    deserialize_raw_prover_params::<B>(&b, domain)
+}
+
+pub fn deserialize_verifier_params<B: Backend>(
+    b: &[u8],
+    domain: Fr,
+) -> Result<VerifierParams<B>, EthDosError> {
+    let b = format::open(
+        Kind::VerifierParams,
+        B::SCHEME,
+        circuit_digest()?,
+        domain_bytes(domain),
+        b,
+    )?;
+    deserialize_raw_verifier_params::<B>(&b, domain)
+}
+
+#[cfg(feature = "prover")]
+fn deserialize_raw_prover_params<B: Backend>(
+    b: &[u8],
+    domain: Fr,
+) -> Result<ProverParams<B>, EthDosError> {
+    Ok(B::pp_deserialize_with_mode(
+        b,
+        ark_serialize::Compress::Yes,
+        ark_serialize::Validate::Yes,
+        circuit_params(domain), // fcircuit_params
+    )?)
+}
+
+fn deserialize_raw_verifier_params<B: Backend>(
+    b: &[u8],
+    domain: Fr,
+) -> Result<VerifierParams<B>, EthDosError> {
+    Ok(B::vp_deserialize_with_mode(
+        b,
+        ark_serialize::Compress::Yes,
+        ark_serialize::Validate::Yes,
+        circuit_params(domain), // fcircuit_params
+    )?)
+}
+
+/// Serializes the signature, which is only valid for the given domain.
+pub fn serialize_sig_pk(
+    sig_pk: &SigPk<EdwardsProjective>,
+    domain: Fr,
+) -> Result<Vec<u8>, EthDosError> {
+    // the signatures do not depend on the folding scheme
+    Ok(format::seal(
+        Kind::SigPk,
+        Scheme::Unspecified,
+        circuit_digest()?,
+        domain_bytes(domain),
+        format::NO_PARAMS,
+        false,
+        &sig_pk.to_bytes(),
+    ))
+}
+
+pub fn deserialize_sig_pk(b: &[u8], domain: Fr) -> Result<SigPk<EdwardsProjective>, EthDosError> {
+    let b = format::open(
+        Kind::SigPk,
+        Scheme::Unspecified,
+        circuit_digest()?,
+        domain_bytes(domain),
+        b,
+    )?;
+    SigPk::from_bytes(b)
+}
+
+pub fn serialize_revocations(revocations: &RevocationTree) -> Result<Vec<u8>, EthDosError> {
+    Ok(format::seal(
+        Kind::Revocations,
+        Scheme::Unspecified,
+        circuit_digest()?,
+        format::NO_DOMAIN,
+        format::NO_PARAMS,
+        false,
+        &revocations.to_bytes()?,
+    ))
+}
+
+pub fn deserialize_revocations(b: &[u8]) -> Result<RevocationTree, EthDosError> {
+    let b = format::open(
+        Kind::Revocations,
+        Scheme::Unspecified,
+        circuit_digest()?,
+        format::NO_DOMAIN,
+        b,
+    )?;
+    let poseidon_config = poseidon_canonical_config::<Fr>();
+    RevocationTree::from_bytes(&poseidon_config, &b)
+}
+
+/// Folds the given chain of signatures, where `pks_sigs[0]` is the signature of `pk_0` over
+/// itself, and each `pks_sigs[i]` is the signature of `pk_i` over `pk_{i-1}`.
+#[cfg(feature = "prover")]
+pub fn fold_sigs<B: Backend>(
+    params: &(ProverParams<B>, VerifierParams<B>),
+    domain: Fr,
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+) -> Result<IVCProof<B>, EthDosError> {
+    fold_sigs_with_rng::<B, _>(&mut OsRng, params, domain, pks_sigs, revocations)
+}
+
+/// Same as `fold_sigs`, with the given source of randomness (see `seeded_rng`).
+#[cfg(feature = "prover")]
+pub fn fold_sigs_with_rng<B: Backend, R: CryptoRngCore>(
+    rng: &mut R,
+    params: &(ProverParams<B>, VerifierParams<B>),
+    domain: Fr,
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+) -> Result<IVCProof<B>, EthDosError> {
+    fold_sigs_with_progress::<B, _, _>(
+        rng,
+        params,
+        domain,
+        pks_sigs,
+        revocations,
+        |_| {},
+        &CancellationToken::new(),
+    )
+}
+
+/// Progress of `fold_sigs_with_progress`, reported after each folding step
+#[cfg(feature = "prover")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FoldProgress {
+    /// Number of steps folded
+    pub step: usize,
+    /// Number of steps to fold
+    pub n_steps: usize,
+    /// Time since the start of the folding, in milliseconds
+    pub elapsed_ms: u64,
+    /// Estimated time to fold the remaining steps, in milliseconds
+    pub eta_ms: u64,
+}
+
+/// Token to cancel a folding run from another thread, or from its progress observer. The clones of
+/// a token share its state.
+#[cfg(feature = "prover")]
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+#[cfg(feature = "prover")]
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Cancels the folding runs of the token, which stop before their next step
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Same as `fold_sigs_with_rng`, calling `progress` after each folding step, and failing with
+/// `EthDosError::Cancelled` before the next step once `cancel` is cancelled.
+#[cfg(feature = "prover")]
+pub fn fold_sigs_with_progress<B: Backend, R: CryptoRngCore, P: FnMut(FoldProgress)>(
+    rng: &mut R,
+    params: &(ProverParams<B>, VerifierParams<B>),
+    domain: Fr,
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+    progress: P,
+    cancel: &CancellationToken,
+) -> Result<IVCProof<B>, EthDosError> {
+    let fs = fold_chain::<B, _, _>(
+        rng,
+        params,
+        B::SCHEME,
+        domain,
+        pks_sigs,
+        revocations,
+        progress,
+        cancel,
+    )?;
+    Ok(fs.ivc_proof())
+}
+
+// folds the chain of signatures into a new instance of the folding scheme `FS`, which is either one
+// of the backends or the Nova instance of the 'compress' mode (see `decider.rs`)
+#[cfg(feature = "prover")]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn fold_chain<FS, R, P>(
+    rng: &mut R,
+    params: &(FS::ProverParam, FS::VerifierParam),
+    scheme: Scheme,
+    domain: Fr,
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+    mut progress: P,
+    cancel: &CancellationToken,
+) -> Result<FS, EthDosError>
+where
+    FS: FoldingScheme<G1, G2, FC>,
+    R: CryptoRngCore,
+    P: FnMut(FoldProgress),
+{
+    let poseidon_config = poseidon_canonical_config::<Fr>();
+
+    // set the initial state
+    let first = pks_sigs
+        .first()
+        .ok_or_else(|| EthDosError::Signature("at least one signature is needed".to_string()))?;
+    let z_0 = initial_state(first.pk, revocations.root())?;
+
+    // check the chain natively, before the (much more expensive) folding steps
+    let steps = vouch_steps(&poseidon_config, domain, pks_sigs, revocations)?;
+
+    let f_circuit = FC::new(circuit_params(domain))?;
+
+    // initialize the folding scheme engine
+    let mut fs = FS::init(params, f_circuit, z_0)?;
+    let n_steps = pks_sigs.len();
+
+    let start_full = get_time();
+    for (i, step) in steps.into_iter().enumerate() {
+        if cancel.is_cancelled() {
+            return Err(EthDosError::Cancelled);
+        }
+        let start = get_time();
+        fs.prove_step(&mut *rng, step, None)?;
+        dbg(format!(
+            "{:?}::prove_step {}: {:?}ms",
+            scheme,
+            i + 1,
+            elapsed(start)
+        ));
+        let elapsed_ms = elapsed(start_full);
+        progress(FoldProgress {
+            step: i + 1,
+            n_steps,
+            elapsed_ms,
+            eta_ms: elapsed_ms / (i + 1) as u64 * (n_steps - i - 1) as u64,
+        });
+    }
+    dbg(format!(
+        "{:?}'s all {} steps time: {:?}ms",
+        scheme,
+        n_steps,
+        elapsed(start_full)
+    ));
+
+    Ok(fs)
+}
+
+/// Progress of a batch of chains (see `fold_batch`), reported each time that a chain is finished
+#[cfg(feature = "parallel")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BatchProgress {
+    /// Number of chains folded
+    pub done: usize,
+    /// Number of chains that could not be folded
+    pub failed: usize,
+    /// Number of chains of the batch
+    pub total: usize,
+}
+
+/// Folds the given chains concurrently on `n_threads` threads (by default one per CPU), returns
+/// the result of each chain, so that a broken chain does not fail the rest of the batch.
+#[cfg(feature = "parallel")]
+pub fn fold_batch<B: Backend, P: Fn(BatchProgress) + Sync>(
+    params: &(ProverParams<B>, VerifierParams<B>),
+    domain: Fr,
+    chains: &[Vec<SigPk<EdwardsProjective>>],
+    revocations: &RevocationTree,
+    n_threads: Option<usize>,
+    progress: P,
+) -> Result<Vec<Result<IVCProof<B>, EthDosError>>, EthDosError>
+where
+    ProverParams<B>: Sync,
+    VerifierParams<B>: Sync,
+    IVCProof<B>: Send,
+{
+    par_chains(chains, n_threads, progress, |pks_sigs| {
+        fold_sigs::<B>(params, domain, pks_sigs, revocations)
+    })
+}
+
+// runs `f` over each chain on a thread pool of `n_threads` threads, reporting the progress
+#[cfg(feature = "parallel")]
+pub(crate) fn par_chains<T, F, P>(
+    chains: &[Vec<SigPk<EdwardsProjective>>],
+    n_threads: Option<usize>,
+    progress: P,
+    f: F,
+) -> Result<Vec<Result<T, EthDosError>>, EthDosError>
+where
+    T: Send,
+    F: Fn(&[SigPk<EdwardsProjective>]) -> Result<T, EthDosError> + Sync,
+    P: Fn(BatchProgress) + Sync,
+{
+    // with 0 threads, rayon uses one per CPU
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(n_threads.unwrap_or(0))
+        .build()
+        .map_err(|e| EthDosError::ThreadPool(e.to_string()))?;
+
+    let total = chains.len();
+    let done = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    let start = get_time();
+    let results = pool.install(|| {
+        chains
+            .par_iter()
+            .map(|pks_sigs| {
+                let result = f(pks_sigs);
+                match result {
+                    Ok(_) => done.fetch_add(1, Ordering::SeqCst),
+                    Err(_) => failed.fetch_add(1, Ordering::SeqCst),
+                };
+                progress(BatchProgress {
+                    done: done.load(Ordering::SeqCst),
+                    failed: failed.load(Ordering::SeqCst),
+                    total,
+                });
+                result
+            })
+            .collect()
+    });
+    dbg(format!(
+        "batch of {} chains on {} threads: {:?}ms",
+        total,
+        pool.current_num_threads(),
+        elapsed(start)
+    ));
+    Ok(results)
+}
+
+/// Checks natively the chain of signatures starting at `pk_0` (`pks_sigs[0].pk`) and returns the
+/// external inputs of its vouch steps, with the proofs that they are not revoked.
+#[cfg(feature = "prover")]
+pub(crate) fn vouch_steps(
+    poseidon_config: &PoseidonConfig<Fr>,
+    domain: Fr,
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+) -> Result<Vec<EthDosStep<EdwardsProjective>>, EthDosError> {
+    let mut prev_pk = match pks_sigs.first() {
+        Some(first) => first.pk,
+        None => return Ok(vec![]),
+    };
+    validate_chain(poseidon_config, domain, prev_pk, pks_sigs)?;
+
+    let mut validity = Validity::default();
+    let mut steps = Vec::with_capacity(pks_sigs.len());
+    for (i, pk_sig) in pks_sigs.iter().enumerate() {
+        validity = narrow_validity(validity, pk_sig).map_err(|e| EthDosError::Chain {
+            index: i,
+            reason: e.to_string(),
+        })?;
+        let non_revocation = revocations
+            .non_revocation(prev_pk, pk_sig.pk)
+            .map_err(|e| EthDosError::Chain {
+                index: i,
+                reason: e.to_string(),
+            })?;
+        steps.push(EthDosStep::vouch(*pk_sig, non_revocation));
+        prev_pk = pk_sig.pk;
+    }
+    Ok(steps)
+}
+
+// returns the validity window of the chain after the vouch `sig_pk`, which can not be empty
+#[cfg(feature = "prover")]
+fn narrow_validity(
+    validity: Validity,
+    sig_pk: &SigPk<EdwardsProjective>,
+) -> Result<Validity, EthDosError> {
+    let narrowed = validity.intersect(&sig_pk.validity.unwrap_or_default());
+    if narrowed.not_before >= narrowed.not_after {
+        return Err(EthDosError::Signature(format!(
+            "the validity window of the vouch does not overlap with the one of the chain, valid \
+             from {} until {}",
+            validity.not_before, validity.not_after
+        )));
+    }
+    Ok(narrowed)
+}
+
+/// Extends the given IVC proof by one step, folding the signature of a new public key over its
+/// `pk_i`. Only the last IVC proof is needed, not the previously folded signatures.
+#[cfg(feature = "prover")]
+pub fn extend_proof<B: Backend>(
+    params: (ProverParams<B>, VerifierParams<B>),
+    domain: Fr,
+    ivc_proof: IVCProof<B>,
+    sig_pk: SigPk<EdwardsProjective>,
+    revocations: &RevocationTree,
+) -> Result<IVCProof<B>, EthDosError> {
+    extend_proof_with_rng::<B, _>(&mut OsRng, params, domain, ivc_proof, sig_pk, revocations)
+}
+
+/// Same as `extend_proof`, with the given source of randomness (see `seeded_rng`).
+#[cfg(feature = "prover")]
+pub fn extend_proof_with_rng<B: Backend, R: CryptoRngCore>(
+    rng: &mut R,
+    params: (ProverParams<B>, VerifierParams<B>),
+    domain: Fr,
+    ivc_proof: IVCProof<B>,
+    sig_pk: SigPk<EdwardsProjective>,
+    revocations: &RevocationTree,
+) -> Result<IVCProof<B>, EthDosError> {
+    let poseidon_config = poseidon_canonical_config::<Fr>();
+
+    // check natively that the new signature is over the last public key of the proof
+    let state = ProofState::from_z(B::z_i(&ivc_proof))?;
+    validate_chain(&poseidon_config, domain, state.pk_i, &[sig_pk])?;
+    if state.revocation_root != revocations.root() {
+        return Err(EthDosError::Revocation(
+            "the proof was generated for a different revocation tree, it has to be re-folded"
+                .to_string(),
+        ));
+    }
+    let non_revocation = revocations.non_revocation(state.pk_i, sig_pk.pk)?;
+    narrow_validity(state.validity, &sig_pk)?;
+
+    // rebuild the folding scheme instance from the last IVC proof
+    let start = get_time();
+    let mut fs = B::from_ivc_proof(ivc_proof, circuit_params(domain), params)?;
+    dbg(format!(
+        "{:?} instance restored from IVC proof: {:?}ms",
+        B::SCHEME,
+        elapsed(start)
+    ));
+
+    let start = get_time();
+    fs.prove_step(rng, EthDosStep::vouch(sig_pk, non_revocation), None)?;
+    dbg(format!(
+        "{:?}::prove_step: {:?}ms",
+        B::SCHEME,
+        elapsed(start)
+    ));
+
+    Ok(fs.ivc_proof())
+}
+
+/// Proves the ownership of the last public key `pk_i` of the IVC proof by signing the verifier's
+/// `challenge` natively, which reveals `pk_i` (unlike `decider::present`).
+pub fn prove_ownership<B: Backend>(
+    ivc_proof: &IVCProof<B>,
+    sk: &SecretKey,
+    challenge: Fr,
+) -> Result<SigPk<EdwardsProjective>, EthDosError> {
+    let state = ProofState::from_z(B::z_i(ivc_proof))?;
+    let ownership = keys::sign_ownership(sk, challenge)?;
+    if ownership.pk != state.pk_i {
+        return Err(EthDosError::Key(
+            "the secret key does not correspond to the last public key of the proof".to_string(),
+        ));
+    }
+    Ok(ownership)
+}
+
+/// Same as `verify_proof_state`, but also checking that the holder of the proof owns its `pk_i`,
+/// ie. that `ownership` (see `prove_ownership`) is a signature of `pk_i` over the `challenge`.
+pub fn verify_with_ownership<B: Backend>(
+    vp: VerifierParams<B>,
+    proof: IVCProof<B>,
+    ownership: &SigPk<EdwardsProjective>,
+    challenge: Fr,
+    expected_pk_0: PublicKey<EdwardsProjective>,
+    revocation_root: Fr,
+    now: u64,
+    max_degree: Option<u64>,
+) -> Result<ProofState, EthDosError> {
+    let poseidon_config = poseidon_canonical_config::<Fr>();
+    signature::verify_ownership(&poseidon_config, ownership, challenge)?;
+    verify_proof_state::<B>(
+        vp,
+        proof,
+        expected_pk_0,
+        revocation_root,
+        now,
+        Some(ownership.pk),
+        max_degree,
+    )
+}
+
+// gives a specific error for the proofs generated before the last revocations
+fn check_revocation_root(z_0: &[Fr], revocation_root: Fr) -> Result<(), EthDosError> {
+    check_state_len(z_0)?;
+    if z_0[9] != revocation_root {
+        return Err(EthDosError::Verification(
+            "the proof was not checked against the current revocation tree".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// checks that all the vouches of the chain are valid at `now`
+fn check_validity(validity: &Validity, now: u64) -> Result<(), EthDosError> {
+    if !validity.contains(now) {
+        return Err(EthDosError::Verification(format!(
+            "the chain of vouches is not valid at {}, it is valid from {} until {}",
+            now, validity.not_before, validity.not_after
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that the weight of the chain is at most `max_weight`, and that all its vouches carry one
+/// of the `accepted_labels` (`signature::NO_LABEL` for the vouches without attributes).
+pub fn check_attributes(
+    state: &ProofState,
+    max_weight: Option<u64>,
+    accepted_labels: Option<&[u8]>,
+) -> Result<(), EthDosError> {
+    if let Some(max_weight) = max_weight {
+        if state.weight > max_weight {
+            return Err(EthDosError::Verification(format!(
+                "weight {} is greater than the maximum {}",
+                state.weight, max_weight
+            )));
+        }
+    }
+    if let Some(accepted_labels) = accepted_labels {
+        check_labels(state.labels, accepted_labels)?;
+    }
+    Ok(())
+}
+
+/// Checks that all the vouches of the chain of the presentation carry one of the
+/// `accepted_labels`, see `check_attributes`.
+pub fn check_presentation_labels(
+    presentation: &Presentation,
+    accepted_labels: &[u8],
+) -> Result<(), EthDosError> {
+    check_labels(presentation.labels, accepted_labels)
+}
+
+fn check_labels(labels: u64, accepted_labels: &[u8]) -> Result<(), EthDosError> {
+    let accepted = signature::labels_mask(accepted_labels)?;
+    if labels & !accepted != 0 {
+        return Err(EthDosError::Verification(format!(
+            "the chain contains vouches with labels not accepted, labels bitmask {:#x}",
+            labels
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the public statement `[pk_0, pk_i, degree]` of the IVC proof, without verifying it.
+pub fn proof_state<B: Backend>(proof: &IVCProof<B>) -> Result<ProofState, EthDosError> {
+    ProofState::from_z(B::z_i(proof))
+}
+
+pub fn verify_proof<B: Backend>(
+    vp: VerifierParams<B>,
+    proof: IVCProof<B>,
+) -> Result<(), EthDosError> {
+    B::verify(vp, proof).map_err(|e| EthDosError::Verification(e.to_string()))
+}
+
+/// Verifies the IVC proof and checks its state against the expected `pk_0`, revocation root, time
+/// `now` (in seconds since the unix epoch), and optionally `pk_i` and `max_degree`.
+pub fn verify_proof_state<B: Backend>(
+    vp: VerifierParams<B>,
+    proof: IVCProof<B>,
+    expected_pk_0: PublicKey<EdwardsProjective>,
+    revocation_root: Fr,
+    now: u64,
+    expected_pk_i: Option<PublicKey<EdwardsProjective>>,
+    max_degree: Option<u64>,
+) -> Result<ProofState, EthDosError> {
+    let state = check_state(
+        B::z_0(&proof),
+        B::z_i(&proof),
+        expected_pk_0,
+        revocation_root,
+        now,
+        expected_pk_i,
+        max_degree,
+    )?;
+    verify_proof::<B>(vp, proof)?;
+    Ok(state)
+}
+
+// checks the initial and last states of a proof, see `verify_proof_state`
+pub(crate) fn check_state(
+    z_0: &[Fr],
+    z_i: &[Fr],
+    expected_pk_0: PublicKey<EdwardsProjective>,
+    revocation_root: Fr,
+    now: u64,
+    expected_pk_i: Option<PublicKey<EdwardsProjective>>,
+    max_degree: Option<u64>,
+) -> Result<ProofState, EthDosError> {
+    // the initial state must be [pk_0, pk_0, 0, 0, 0, 0, 0, revocation_root, 0, NO_EXPIRY, 0, 0],
+    // otherwise the chain would not start at pk_0, would not have been checked against the
+    // current revocations, or its validity window, weight and labels would not be the ones of its
+    // vouches
+    check_revocation_root(z_0, revocation_root)?;
+    if z_0 != initial_state(expected_pk_0, revocation_root)?.as_slice() {
+        return Err(EthDosError::Verification(
+            "initial state is not [pk_0, pk_0, 0, 0, 0, 0, 0, revocation_root, 0, NO_EXPIRY, 0, 0] \
+             for the expected pk_0"
+                .to_string(),
+        ));
+    }
+    let state = ProofState::from_z(z_i)?;
+    if state.pk_0 != expected_pk_0 {
+        return Err(EthDosError::Verification(
+            "pk_0 does not match the expected one".to_string(),
+        ));
+    }
+    if let Some(pk_i) = expected_pk_i {
+        if state.pk_i != pk_i {
+            return Err(EthDosError::Verification(
+                "pk_i does not match the expected one".to_string(),
+            ));
+        }
+    }
+    if let Some(max_degree) = max_degree {
+        if state.degree > max_degree {
+            return Err(EthDosError::Verification(format!(
+                "degree {} is greater than the maximum {}",
+                state.degree, max_degree
+            )));
+        }
+    }
+    check_validity(&state.validity, now)?;
+    Ok(state)
+}
+
+// checks the initial and last states of a presentation proof, see `decider::verify_presentation`
+pub(crate) fn check_presentation(
+    z_0: &[Fr],
+    z_i: &[Fr],
+    expected_pk_0: PublicKey<EdwardsProjective>,
+    revocation_root: Fr,
+    now: u64,
+    challenge: Fr,
+    max_degree: u64,
+) -> Result<Presentation, EthDosError> {
+    check_revocation_root(z_0, revocation_root)?;
+    if z_0 != initial_state(expected_pk_0, revocation_root)?.as_slice() {
+        return Err(EthDosError::Verification(
+            "the proof does not start at the expected pk_0".to_string(),
+        ));
+    }
+    let presentation = Presentation::from_z(z_i)?;
+    if presentation.challenge != challenge {
+        return Err(EthDosError::Verification(
+            "the proof answers a different challenge".to_string(),
+        ));
+    }
+    if presentation.max_degree != max_degree {
+        return Err(EthDosError::Verification(format!(
+            "the proof compares the degree with {} instead of {}",
+            presentation.max_degree, max_degree
+        )));
+    }
+    if !presentation.within_max_degree {
+        return Err(EthDosError::Verification(format!(
+            "the degree is greater than the max_degree {}",
+            max_degree
+        )));
+    }
+    check_validity(&presentation.validity, now)?;
+    Ok(presentation)
+}
+
+/// Serializes the IVC proof of the chains of the given domain and compresses it. `params_id` is the
+/// id of the params with which the proof was generated (see `verifier_params_id`).
+pub fn serialize_ivc_proof<B: Backend>(
+    ivc_proof: &IVCProof<B>,
+    domain: Fr,
+    params_id: [u8; 32],
+) -> Result<Vec<u8>, EthDosError> {
+    let mut ivc_proof_bytes = vec![];
+    ivc_proof.serialize_compressed(&mut ivc_proof_bytes)?;
+
+    let ivc_proof_bytes_comp = format::seal(
+        Kind::IVCProof,
+        B::SCHEME,
+        circuit_digest()?,
+        domain_bytes(domain),
+        params_id,
+        true,
+        &ivc_proof_bytes,
+    );
+
+    dbg(format!(
+        "ivc_proof size (uncompressed): {} mb",
+        ivc_proof_bytes.len() / (1024 * 1024)
+    ));
+    dbg(format!(
+        "ivc_proof size (compressed): {} mb",
+        ivc_proof_bytes_comp.len() / (1024 * 1024)
+    ));
+
+    Ok(ivc_proof_bytes_comp)
+}
+
+/// Inverse of `serialize_ivc_proof`
+pub fn deserialize_ivc_proof<B: Backend>(b: &[u8], domain: Fr) -> Result<IVCProof<B>, EthDosError> {
+    let ivc_proof_bytes = format::open(
+        Kind::IVCProof,
+        B::SCHEME,
+        circuit_digest()?,
+        domain_bytes(domain),
+        b,
+    )?;
+    Ok(IVCProof::<B>::deserialize_compressed(
+        ivc_proof_bytes.as_slice(),
+    )?)
+}
+
+pub(crate) fn pk_from_xy(x: Fr, y: Fr) -> Result<PublicKey<EdwardsProjective>, EthDosError> {
+    let p = <EdwardsProjective as CurveGroup>::Affine::new_unchecked(x, y);
+    if !p.is_on_curve() || !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(EthDosError::Verification(
+            "state contains an invalid public key".to_string(),
+        ));
+    }
+    Ok(PublicKey(p))
+}
+
+pub(crate) fn fr_to_u64(v: Fr) -> Result<u64, EthDosError> {
+    let b = v.into_bigint();
+    if b.num_bits() > 64 {
+        return Err(EthDosError::Verification(
+            "value does not fit in a u64".to_string(),
+        ));
+    }
+    Ok(b.as_ref()[0])
+}
+
+#[cfg(all(test, feature = "prover"))]
+pub(crate) mod tests {
+    use blake2::{Blake2s256, Digest};
+    use rand::rngs::OsRng;
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::backend::NovaFS;
+    use crate::signature::{
+        self, gen_signatures, labels_mask, Attributes, DEFAULT_WEIGHT, NO_LABEL,
+    };
+
+    // current time of the tests, in seconds since the unix epoch
+    const NOW: u64 = 1_700_000_000;
+
+    // Nova params of the default domain and empty revocation tree, shared by the tests
+    pub(crate) struct Fixture {
+        pub(crate) poseidon_config: PoseidonConfig<Fr>,
+        pub(crate) domain: Fr,
+        pub(crate) params: (ProverParams<NovaFS>, VerifierParams<NovaFS>),
+        pub(crate) revocations: RevocationTree,
+    }
+    impl Fixture {
+        pub(crate) fn new() -> Self {
+            let poseidon_config = poseidon_canonical_config::<Fr>();
+            let domain = default_domain();
+            Self {
+                params: gen_params::<NovaFS>(domain).unwrap(),
+                revocations: RevocationTree::new(&poseidon_config),
+                poseidon_config,
+                domain,
+            }
+        }
+        // chain of n signatures of random keys
+        pub(crate) fn sigs(&self, n: usize) -> Vec<SigPk<EdwardsProjective>> {
+            gen_signatures::<OsRng, EdwardsProjective>(
+                &mut OsRng,
+                &self.poseidon_config,
+                self.domain,
+                n,
+            )
+            .unwrap()
+        }
+        pub(crate) fn fold(&self, sigs: &[SigPk<EdwardsProjective>]) -> IVCProof<NovaFS> {
+            fold_sigs::<NovaFS>(&self.params, self.domain, sigs, &self.revocations).unwrap()
+        }
+    }
+
+    // test that a proof of degree N can be extended to degree N+1 by only knowing the last IVC
+    // proof and the new signature
+    #[test]
+    fn test_extend_proof() {
+        const N_STEPS: usize = 3;
+        let f = Fixture::new();
+        let (params, domain, revocations) = (&f.params, f.domain, &f.revocations);
+        let sigs = f.sigs(N_STEPS + 1);
+        let ivc_proof = f.fold(&sigs[..N_STEPS]);
+        verify_proof::<NovaFS>(params.1.clone(), ivc_proof.clone()).unwrap();
+
+        // a signature that is not over the last public key of the proof is rejected before
+        // proving
+        assert!(matches!(
+            extend_proof::<NovaFS>(
+                params.clone(),
+                domain,
+                ivc_proof.clone(),
+                sigs[N_STEPS - 1],
+                revocations
+            ),
+            Err(EthDosError::Chain { index: 0, .. })
+        ));
+        // and so is a signature over the last public key but of another domain
+        let (sk, _) = keys::keygen(&mut OsRng).unwrap();
+        let other = keys::sign_pk(&sk, domain_tag("other"), sigs[N_STEPS - 1].pk).unwrap();
+        assert!(matches!(
+            extend_proof::<NovaFS>(
+                params.clone(),
+                domain,
+                ivc_proof.clone(),
+                other,
+                revocations
+            ),
+            Err(EthDosError::Chain { index: 0, .. })
+        ));
+
+        let ivc_proof = extend_proof::<NovaFS>(
+            params.clone(),
+            domain,
+            ivc_proof,
+            sigs[N_STEPS],
+            revocations,
+        )
+        .unwrap();
+        verify_proof::<NovaFS>(params.1.clone(), ivc_proof.clone()).unwrap();
+        assert_eq!(ivc_proof.z_i[4], Fr::from((N_STEPS + 1) as u32));
+    }
+
+    // test that the progress is reported after each step, and that a cancelled folding stops
+    // before its next step
+    #[test]
+    fn test_fold_progress() {
+        const N_STEPS: usize = 3;
+        let f = Fixture::new();
+        let (params, domain, revocations) = (&f.params, f.domain, &f.revocations);
+        let sigs = f.sigs(N_STEPS);
+
+        let mut reports = vec![];
+        let cancel = CancellationToken::new();
+        let ivc_proof = fold_sigs_with_progress::<NovaFS, _, _>(
+            &mut OsRng,
+            params,
+            domain,
+            &sigs,
+            revocations,
+            |p| reports.push(p),
+            &cancel,
+        )
+        .unwrap();
+        assert_eq!(ivc_proof.z_i[4], Fr::from(N_STEPS as u32));
+        let steps: Vec<usize> = reports.iter().map(|p| p.step).collect();
+        assert_eq!(steps, vec![1, 2, 3]);
+        assert!(reports.iter().all(|p| p.n_steps == N_STEPS));
+        assert_eq!(reports[N_STEPS - 1].eta_ms, 0);
+
+        // cancelled by the observer after the first step
+        let mut reports = vec![];
+        let cancel = CancellationToken::new();
+        let result = fold_sigs_with_progress::<NovaFS, _, _>(
+            &mut OsRng,
+            params,
+            domain,
+            &sigs,
+            revocations,
+            |p| {
+                reports.push(p);
+                cancel.cancel();
+            },
+            &cancel,
+        );
+        assert!(matches!(result, Err(EthDosError::Cancelled)));
+        assert_eq!(reports.len(), 1);
+
+        // and an already cancelled token does not fold any step
+        assert!(matches!(
+            fold_sigs_with_progress::<NovaFS, _, _>(
+                &mut OsRng,
+                params,
+                domain,
+                &sigs,
+                revocations,
+                |_| panic!("no step expected"),
+                &cancel,
+            ),
+            Err(EthDosError::Cancelled)
+        ));
+    }
+
+    // test that the chains of a batch are folded independently, a broken chain only failing its
+    // own result
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_fold_batch() {
+        let f = Fixture::new();
+        let params = &f.params;
+
+        let mut chains: Vec<Vec<SigPk<EdwardsProjective>>> = (1..=3).map(|n| f.sigs(n)).collect();
+        // a chain with its vouches out of order is broken
+        let mut broken = chains[2].clone();
+        broken.swap(1, 2);
+        chains.push(broken);
+
+        let reports = std::sync::Mutex::new(vec![]);
+        let proofs =
+            fold_batch::<NovaFS, _>(params, f.domain, &chains, &f.revocations, Some(2), |p| {
+                reports.lock().unwrap().push(p)
+            })
+            .unwrap();
+        assert_eq!(proofs.len(), chains.len());
+        for (i, proof) in proofs[..3].iter().enumerate() {
+            let proof = proof.as_ref().unwrap();
+            assert_eq!(proof.z_i[4], Fr::from((i + 1) as u32));
+            verify_proof::<NovaFS>(params.1.clone(), proof.clone()).unwrap();
+        }
+        assert!(proofs[3].is_err());
+
+        // one report per chain, one of them with the final counts
+        let reports = reports.into_inner().unwrap();
+        assert_eq!(reports.len(), chains.len());
+        assert!(reports.contains(&BatchProgress {
+            done: 3,
+            failed: 1,
+            total: 4
+        }));
+    }
+
+    // test that the verifier rejects valid proofs that are about a different statement than the
+    // expected one
+    #[test]
+    fn test_verify_proof_state() {
+        const N_STEPS: usize = 3;
+        let f = Fixture::new();
+        let sigs = f.sigs(N_STEPS);
+        let root = f.revocations.root();
+        let proof = f.fold(&sigs);
+        let vp = f.params.1.clone();
+
+        let state = verify_proof_state::<NovaFS>(
+            vp.clone(),
+            proof.clone(),
+            sigs[0].pk,
+            root,
+            NOW,
+            Some(sigs[N_STEPS - 1].pk),
+            Some(N_STEPS as u64),
+        )
+        .unwrap();
+        assert_eq!(state.degree, N_STEPS as u64);
+
+        let other_pk = f.sigs(1)[0].pk;
+        // unrelated pk_0
+        assert!(verify_proof_state::<NovaFS>(
+            vp.clone(),
+            proof.clone(),
+            other_pk,
+            root,
+            NOW,
+            None,
+            None
+        )
+        .is_err());
+        // unexpected pk_i
+        assert!(verify_proof_state::<NovaFS>(
+            vp.clone(),
+            proof.clone(),
+            sigs[0].pk,
+            root,
+            NOW,
+            Some(other_pk),
+            None
+        )
+        .is_err());
+        // degree above the maximum
+        assert!(verify_proof_state::<NovaFS>(
+            vp,
+            proof,
+            sigs[0].pk,
+            root,
+            NOW,
+            None,
+            Some(N_STEPS as u64 - 1)
+        )
+        .is_err());
+    }
+
+    // test that the folding scheme rejects the IVC proofs whose public values are tampered, so
+    // that the state can not be changed after folding
+    #[test]
+    fn test_tampered_proof() {
+        let f = Fixture::new();
+        let sigs = f.sigs(3);
+        let proof = f.fold(&sigs);
+        let vp = f.params.1.clone();
+        verify_proof::<NovaFS>(vp.clone(), proof.clone()).unwrap();
+
+        let other_pk = sigs[1].pk.0.xy().unwrap();
+        let tamper: Vec<fn(&mut IVCProof<NovaFS>)> = vec![
+            // degree not matching the number of steps
+            |p| p.z_i[4] += Fr::from(1_u32),
+            // pk_0 altered, in the initial or in the last state
+            |p| p.z_0[0] += Fr::from(1_u32),
+            |p| p.z_i[0] += Fr::from(1_u32),
+            // number of steps
+            |p| p.i += Fr::from(1_u32),
+            // folded instance
+            |p| p.U_i.u += Fr::from(1_u32),
+        ];
+        for f in tamper {
+            let mut tampered = proof.clone();
+            f(&mut tampered);
+            assert!(verify_proof::<NovaFS>(vp.clone(), tampered).is_err());
+        }
+        // last public key replaced by another one of the chain
+        let mut tampered = proof;
+        tampered.z_i[2] = other_pk.0;
+        tampered.z_i[3] = other_pk.1;
+        assert!(verify_proof::<NovaFS>(vp, tampered).is_err());
+    }
+
+    #[test]
+    fn test_ownership() {
+        let f = Fixture::new();
+        let (sk_0, pk_0) = keys::keygen(&mut OsRng).unwrap();
+        let (sk_1, _) = keys::keygen(&mut OsRng).unwrap();
+        let sigs = vec![
+            keys::sign_pk(&sk_0, f.domain, pk_0).unwrap(),
+            keys::sign_pk(&sk_1, f.domain, pk_0).unwrap(),
+        ];
+
+        let vp = f.params.1.clone();
+        let root = f.revocations.root();
+        let proof = f.fold(&sigs);
+        let challenge = Fr::from(42_u32);
+
+        // only the owner of the last public key can prove the ownership
+        assert!(matches!(
+            prove_ownership::<NovaFS>(&proof, &sk_0, challenge),
+            Err(EthDosError::Key(_))
+        ));
+        let ownership = prove_ownership::<NovaFS>(&proof, &sk_1, challenge).unwrap();
+        let state = verify_with_ownership::<NovaFS>(
+            vp.clone(),
+            proof.clone(),
+            &ownership,
+            challenge,
+            pk_0,
+            root,
+            NOW,
+            Some(2),
+        )
+        .unwrap();
+        assert_eq!(state.degree, 2);
+
+        // the ownership proof is bound to the challenge
+        assert!(verify_with_ownership::<NovaFS>(
+            vp.clone(),
+            proof.clone(),
+            &ownership,
+            Fr::from(43_u32),
+            pk_0,
+            root,
+            NOW,
+            None
+        )
+        .is_err());
+        // a forwarded proof can not be claimed with another key's ownership proof
+        let other = keys::sign_ownership(&sk_0, challenge).unwrap();
+        assert!(verify_with_ownership::<NovaFS>(
+            vp, proof, &other, challenge, pk_0, root, NOW, None
+        )
+        .is_err());
+        // the presentation signature of the same challenge is not an ownership proof
+        let presentation_sig = keys::sign_challenge(&sk_1, challenge).unwrap();
+        assert!(
+            signature::verify_ownership(&f.poseidon_config, &presentation_sig, challenge).is_err()
+        );
+    }
+
+    // test that the revoked public keys and vouches can not be folded, and that the proofs
+    // generated before a revocation are rejected
+    #[test]
+    fn test_revocation() {
+        let f = Fixture::new();
+        let (params, domain) = (&f.params, f.domain);
+        let vp = params.1.clone();
+        let sigs = f.sigs(3);
+        let (pk_0, pk_1, pk_2) = (sigs[0].pk, sigs[1].pk, sigs[2].pk);
+
+        let old_root = f.revocations.root();
+        let proof = f.fold(&sigs[..2]);
+        let mut revocations = f.revocations.clone();
+
+        // revoke the vouch of pk_2 for pk_1
+        revocations.revoke_vouch(pk_1, pk_2).unwrap();
+        assert!(matches!(
+            fold_sigs::<NovaFS>(params, domain, &sigs, &revocations),
+            Err(EthDosError::Chain { index: 2, .. })
+        ));
+        // the proof generated before the revocation can not be extended with the new tree, nor
+        // verified against the new root
+        assert!(matches!(
+            extend_proof::<NovaFS>(params.clone(), domain, proof.clone(), sigs[2], &revocations),
+            Err(EthDosError::Revocation(_))
+        ));
+        assert!(verify_proof_state::<NovaFS>(
+            vp.clone(),
+            proof.clone(),
+            pk_0,
+            revocations.root(),
+            NOW,
+            None,
+            None
+        )
+        .is_err());
+        verify_proof_state::<NovaFS>(vp.clone(), proof, pk_0, old_root, NOW, None, None).unwrap();
+
+        // the chain up to pk_1 is still valid with the new tree
+        let proof = fold_sigs::<NovaFS>(params, domain, &sigs[..2], &revocations).unwrap();
+        let state = verify_proof_state::<NovaFS>(
+            vp,
+            proof,
+            pk_0,
+            revocations.root(),
+            NOW,
+            Some(pk_1),
+            None,
+        )
+        .unwrap();
+        assert_eq!(state.revocation_root, revocations.root());
+
+        // revoking pk_0 invalidates all its chains
+        revocations.revoke_pk(pk_0).unwrap();
+        assert!(matches!(
+            fold_sigs::<NovaFS>(params, domain, &sigs[..1], &revocations),
+            Err(EthDosError::Chain { index: 0, .. })
+        ));
+    }
+
+    // test that the verifier rejects the chains with a vouch that is expired or not yet valid
+    #[test]
+    fn test_expiring_vouches() {
+        let f = Fixture::new();
+        let (params, domain) = (&f.params, f.domain);
+        let (sk_0, pk_0) = keys::keygen(&mut OsRng).unwrap();
+        let (sk_1, pk_1) = keys::keygen(&mut OsRng).unwrap();
+        let (sk_2, _) = keys::keygen(&mut OsRng).unwrap();
+        let year = 365 * 24 * 3600;
+        let sigs = vec![
+            keys::sign_pk(&sk_0, domain, pk_0).unwrap(),
+            keys::sign_pk_with_validity(
+                &sk_1,
+                domain,
+                pk_0,
+                Validity::new(NOW - year, NOW + year).unwrap(),
+            )
+            .unwrap(),
+            keys::sign_pk_with_validity(
+                &sk_2,
+                domain,
+                pk_1,
+                Validity::new(NOW - 10, NOW + 2 * year).unwrap(),
+            )
+            .unwrap(),
+        ];
+
+        let vp = params.1.clone();
+        let root = f.revocations.root();
+        let proof = f.fold(&sigs);
+
+        // the window of the chain is the intersection of the windows of its vouches
+        let state =
+            verify_proof_state::<NovaFS>(vp.clone(), proof.clone(), pk_0, root, NOW, None, None)
+                .unwrap();
+        assert_eq!(state.validity, Validity::new(NOW - 10, NOW + year).unwrap());
+        // expired, and not yet valid
+        for now in [NOW + year, NOW - 11] {
+            assert!(verify_proof_state::<NovaFS>(
+                vp.clone(),
+                proof.clone(),
+                pk_0,
+                root,
+                now,
+                None,
+                None
+            )
+            .is_err());
+        }
+
+        // a vouch that does not overlap with the window of the chain is rejected before folding
+        let (sk_3, _) = keys::keygen(&mut OsRng).unwrap();
+        let expired = Validity::new(NOW - 2 * year, NOW - year).unwrap();
+        let mut sigs = sigs;
+        sigs.push(keys::sign_pk_with_validity(&sk_3, domain, sigs[2].pk, expired).unwrap());
+        assert!(matches!(
+            fold_sigs::<NovaFS>(params, domain, &sigs, &f.revocations),
+            Err(EthDosError::Chain { index: 3, .. })
+        ));
+
+        // the window survives the serialization of the signatures
+        let b = serialize_sig_pk(&sigs[3], domain).unwrap();
+        assert_eq!(
+            deserialize_sig_pk(&b, domain).unwrap().validity,
+            Some(expired)
+        );
+    }
+
+    #[test]
+    fn test_vouch_attributes() {
+        const IN_PERSON: u8 = 1;
+        const ONLINE: u8 = 2;
+        let f = Fixture::new();
+        let (params, domain) = (&f.params, f.domain);
+        let (sk_0, pk_0) = keys::keygen(&mut OsRng).unwrap();
+        let (sk_1, pk_1) = keys::keygen(&mut OsRng).unwrap();
+        let (sk_2, pk_2) = keys::keygen(&mut OsRng).unwrap();
+        let (sk_3, _) = keys::keygen(&mut OsRng).unwrap();
+        let in_person = Attributes::new(IN_PERSON, 2).unwrap();
+        let sigs = vec![
+            keys::sign_pk(&sk_0, domain, pk_0).unwrap(),
+            keys::sign_vouch(&sk_1, domain, pk_0, None, Some(in_person)).unwrap(),
+            keys::sign_vouch(&sk_2, domain, pk_1, None, Some(in_person)).unwrap(),
+            keys::sign_vouch(
+                &sk_3,
+                domain,
+                pk_2,
+                None,
+                Some(Attributes::new(ONLINE, 5).unwrap()),
+            )
+            .unwrap(),
+        ];
+
+        let vp = params.1.clone();
+        let root = f.revocations.root();
+
+        // the first vouch has no attributes, so it has the default weight and no label
+        let proof = f.fold(&sigs[..3]);
+        let state =
+            verify_proof_state::<NovaFS>(vp.clone(), proof.clone(), pk_0, root, NOW, None, None)
+                .unwrap();
+        assert_eq!(state.weight, DEFAULT_WEIGHT as u64 + 4);
+        assert_eq!(state.labels, labels_mask(&[NO_LABEL, IN_PERSON]).unwrap());
+        check_attributes(&state, Some(5), Some(&[NO_LABEL, IN_PERSON])).unwrap();
+        assert!(check_attributes(&state, Some(4), None).is_err());
+        assert!(check_attributes(&state, None, Some(&[IN_PERSON])).is_err());
+
+        let proof =
+            extend_proof::<NovaFS>(params.clone(), domain, proof, sigs[3], &f.revocations).unwrap();
+        let state =
+            verify_proof_state::<NovaFS>(vp.clone(), proof.clone(), pk_0, root, NOW, None, None)
+                .unwrap();
+        assert_eq!(state.weight, DEFAULT_WEIGHT as u64 + 9);
+        assert!(check_attributes(&state, None, Some(&[NO_LABEL, IN_PERSON])).is_err());
+        check_attributes(&state, None, Some(&[NO_LABEL, IN_PERSON, ONLINE])).unwrap();
+
+        // the attributes survive the serialization of the signatures
+        let b = serialize_sig_pk(&sigs[1], domain).unwrap();
+        assert_eq!(
+            deserialize_sig_pk(&b, domain).unwrap().attributes,
+            Some(in_person)
+        );
+    }
+
+    // path of the test vectors of `test_vectors`, generated from VECTORS_SEED
+    const VECTORS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/vectors.txt");
+    const VECTORS_SEED: u64 = 42;
+    const VECTORS_HEADER: &str =
+        "# vectors of api::tests::test_vectors, regenerated with ETHDOS_UPDATE_VECTORS=1\n";
+
+    fn to_hex(b: &[u8]) -> String {
+        b.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // regression test of the serialization formats and of the outputs of the circuit, against the
+    // vectors at VECTORS_PATH. A missing or changed vector fails the test, and all of them are
+    // regenerated with ETHDOS_UPDATE_VECTORS=1 after an intended change (eg. of the
+    // CIRCUIT_VERSION or of the Sonobe revision).
+    #[test]
+    fn test_vectors() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let domain = default_domain();
+        let revocations = RevocationTree::new(&poseidon_config);
+
+        let mut rng = seeded_rng(VECTORS_SEED);
+        let (sk, pk) = keys::keygen(&mut rng).unwrap();
+        let sigs =
+            gen_signatures::<ChaCha20Rng, EdwardsProjective>(&mut rng, &poseidon_config, domain, 3)
+                .unwrap();
+        let params = gen_params_with_rng::<NovaFS, _>(&mut rng, domain).unwrap();
+        let proof = fold_sigs_with_rng::<NovaFS, _>(&mut rng, &params, domain, &sigs, &revocations)
+            .unwrap();
+
+        // the same seed gives the same keys and signatures
+        let mut rng = seeded_rng(VECTORS_SEED);
+        assert_eq!(keys::keygen(&mut rng).unwrap().1, pk);
+        assert_eq!(
+            gen_signatures::<ChaCha20Rng, EdwardsProjective>(&mut rng, &poseidon_config, domain, 3)
+                .unwrap(),
+            sigs
+        );
+
+        let vp = serialize_verifier_params::<NovaFS>(&params.1, domain).unwrap();
+        let mut vectors = vec![
+            ("domain".to_string(), domain.to_string()),
+            ("secret_key".to_string(), to_hex(&sk.to_bytes())),
+            ("public_key".to_string(), to_hex(&pk.to_bytes())),
+            (
+                "circuit_digest".to_string(),
+                to_hex(&circuit_digest().unwrap()),
+            ),
+            (
+                "verifier_params_digest".to_string(),
+                to_hex(&Blake2s256::digest(vp)),
+            ),
+            (
+                "params_id".to_string(),
+                to_hex(&verifier_params_id::<NovaFS>(&params.1, domain).unwrap()),
+            ),
+        ];
+        for (i, sig) in sigs.iter().enumerate() {
+            vectors.push((
+                format!("sig_pk_{}", i),
+                to_hex(&serialize_sig_pk(sig, domain).unwrap()),
+            ));
+        }
+        let z_i: Vec<String> = NovaFS::z_i(&proof).iter().map(|v| v.to_string()).collect();
+        vectors.push(("z_i".to_string(), z_i.join(",")));
+
+        // the vectors are only written when explicitly requested, otherwise a missing or changed
+        // vector fails the test
+        if std::env::var("ETHDOS_UPDATE_VECTORS").is_ok() {
+            let lines: String = vectors
+                .iter()
+                .map(|(name, value)| format!("{} = {}\n", name, value))
+                .collect();
+            std::fs::write(VECTORS_PATH, [VECTORS_HEADER, lines.as_str()].concat()).unwrap();
+            return;
+        }
+        let file = std::fs::read_to_string(VECTORS_PATH)
+            .unwrap_or_else(|e| panic!("can not read {}: {}", VECTORS_PATH, e));
+        let expected: HashMap<&str, &str> =
+            file.lines().filter_map(|l| l.split_once(" = ")).collect();
+        for (name, value) in &vectors {
+            match expected.get(name.as_str()) {
+                Some(v) => assert_eq!(*v, value.as_str(), "test vector {} changed", name),
+                None => panic!(
+                    "test vector {} is missing, regenerate them with ETHDOS_UPDATE_VECTORS=1",
+                    name
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonical_params() {
+        let domain = default_domain();
+        let (pp, vp) = gen_canonical_params::<NovaFS>(domain).unwrap();
+        let id = verifier_params_id::<NovaFS>(&vp, domain).unwrap();
+        assert_eq!(prover_params_id::<NovaFS>(&pp, domain).unwrap(), id);
+
+        // the canonical params are the same at each generation, and differ from random ones
+        let (_, vp_again) = gen_canonical_params::<NovaFS>(domain).unwrap();
+        assert_eq!(verifier_params_id::<NovaFS>(&vp_again, domain).unwrap(), id);
+        let (_, vp_random) = gen_params::<NovaFS>(domain).unwrap();
+        assert_ne!(
+            verifier_params_id::<NovaFS>(&vp_random, domain).unwrap(),
+            id
+        );
+
+        // the generators of the commitments are derived by hash-to-curve from the canonical seed
+        let seed = format::canonical_seed(
+            circuit_digest().unwrap(),
+            Scheme::Nova,
+            domain_bytes(domain),
+        );
+        let (cs_params, cf_cs_params) = NovaFS::pp_commitment_params(&pp);
+        let (h, generators) = hash_to_generators::<<G1 as CurveGroup>::Config>(
+            seed,
+            b"cs",
+            cs_params.generators.len(),
+        );
+        assert_eq!(cs_params.h, h);
+        assert_eq!(cs_params.generators, generators);
+        let (h, generators) = hash_to_generators::<<G2 as CurveGroup>::Config>(
+            seed,
+            b"cf_cs",
+            cf_cs_params.generators.len(),
+        );
+        assert_eq!(cf_cs_params.h, h);
+        assert_eq!(cf_cs_params.generators, generators);
+        assert!(generators.iter().all(|g| g.is_on_curve() && !g.is_zero()));
+
+        // the serialized params carry their id
+        let b = serialize_verifier_params::<NovaFS>(&vp, domain).unwrap();
+        assert_eq!(format::read_header(&b).unwrap().params_id, id);
+        assert_eq!(
+            serialize_verifier_params::<NovaFS>(&vp_again, domain).unwrap(),
+            b
+        );
+    }
+
+    #[test]
+    fn test_malformed_inputs() {
+        let domain = default_domain();
+        assert!(matches!(
+            SigPk::<EdwardsProjective>::from_bytes(vec![1, 2, 3]),
+            Err(EthDosError::Deserialization(_))
+        ));
+        assert!(matches!(
+            deserialize_ivc_proof::<NovaFS>(&[0, 1, 2, 3, 4, 5], domain),
+            Err(EthDosError::Format(_))
+        ));
+        assert!(deserialize_verifier_params::<NovaFS>(&[0u8; 32], domain).is_err());
+
+        // a signature is not accepted where a proof is expected
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let sigs =
+            gen_signatures::<OsRng, EdwardsProjective>(&mut OsRng, &poseidon_config, domain, 1)
+                .unwrap();
+        let b = serialize_sig_pk(&sigs[0], domain).unwrap();
+        assert_eq!(deserialize_sig_pk(&b, domain).unwrap(), sigs[0]);
+        assert!(matches!(
+            deserialize_ivc_proof::<NovaFS>(&b, domain),
+            Err(EthDosError::Format(_))
+        ));
+
+        // a signature of another domain is rejected
+        let other_domain = domain_tag::<Fr>("other");
+        assert!(matches!(
+            deserialize_sig_pk(&b, other_domain),
+            Err(EthDosError::Format(_))
+        ));
+        assert_eq!(artefact_domain(&b).unwrap(), domain);
+    }
+}
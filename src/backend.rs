@@ -0,0 +1,766 @@
+//! This file contains the `Backend` trait over the folding schemes, and at the bottom the methods
+//! over the serialized artefacts, dispatched to the folding scheme of their header.
+use ark_bn254::{Fr, G1Projective as G1};
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_grumpkin::Projective as G2;
+use ark_serialize::{CanonicalSerialize, Compress, Validate};
+use rand::rngs::OsRng;
+use rand_core::CryptoRngCore;
+
+use arkeddsa::ed_on_bn254_twist::EdwardsProjective;
+use arkeddsa::PublicKey;
+
+#[cfg(feature = "hypernova")]
+use folding_schemes::folding::hypernova::{self, HyperNova};
+#[cfg(feature = "protogalaxy")]
+use folding_schemes::folding::protogalaxy::ProtoGalaxy;
+use folding_schemes::{
+    commitment::pedersen::{self, Pedersen},
+    folding::nova::{self, Nova},
+    FoldingScheme,
+};
+
+#[cfg(feature = "parallel")]
+use crate::api::BatchProgress;
+#[cfg(feature = "decider")]
+use crate::api::Presentation;
+use crate::api::{self, ProofState, FC};
+#[cfg(feature = "prover")]
+use crate::api::{CancellationToken, FoldProgress};
+#[cfg(feature = "decider")]
+use crate::decider;
+use crate::error::EthDosError;
+use crate::fcircuit::EthDosParams;
+use crate::format::{self, Kind, Scheme};
+use crate::keys::SecretKey;
+use crate::revocation::RevocationTree;
+use crate::signature::SigPk;
+
+pub type NovaFS = Nova<G1, G2, FC, Pedersen<G1>, Pedersen<G2>, false>;
+#[cfg(feature = "hypernova")]
+pub type HyperNovaFS = HyperNova<G1, G2, FC, Pedersen<G1>, Pedersen<G2>, 1, 1, false>;
+#[cfg(feature = "protogalaxy")]
+pub type ProtoGalaxyFS = ProtoGalaxy<G1, G2, FC, Pedersen<G1>, Pedersen<G2>>;
+
+/// Folding scheme over which the EthDosCircuit is folded. It adds to Sonobe's `FoldingScheme`
+/// trait the parts that are not common among the folding schemes.
+pub trait Backend: FoldingScheme<G1, G2, FC> {
+    /// Identifier of the folding scheme, stored in the header of the serialized artefacts
+    const SCHEME: Scheme;
+
+    fn preprocessor_param(
+        poseidon_config: PoseidonConfig<Fr>,
+        f_circuit: FC,
+    ) -> Self::PreprocessorParam;
+
+    /// Verifier params corresponding to the given prover params, which contain the same commitment
+    /// params (for Pedersen, the prover and verifier params of the commitment scheme are the same)
+    fn verifier_params(
+        fcircuit_params: EthDosParams<Fr>,
+        pp: &Self::ProverParam,
+    ) -> Result<Self::VerifierParam, EthDosError> {
+        // the serialized verifier params are their commitment params, from which Sonobe builds
+        // them by computing the R1CS of the circuit, without sampling new commitment params
+        let (cs_params, cf_cs_params) = Self::pp_commitment_params(pp);
+        let mut b = vec![];
+        cs_params.serialize_compressed(&mut b)?;
+        cf_cs_params.serialize_compressed(&mut b)?;
+        Ok(Self::vp_deserialize_with_mode(
+            &b[..],
+            Compress::Yes,
+            Validate::No,
+            fcircuit_params,
+        )?)
+    }
+
+    /// Commitment params of the prover params, from which their id is computed (see
+    /// `api::prover_params_id`)
+    fn pp_commitment_params(
+        pp: &Self::ProverParam,
+    ) -> (&pedersen::Params<G1>, &pedersen::Params<G2>);
+    /// Mutable commitment params of the prover params, to replace them by the canonical ones (see
+    /// `api::gen_canonical_params`)
+    fn pp_commitment_params_mut(
+        pp: &mut Self::ProverParam,
+    ) -> (&mut pedersen::Params<G1>, &mut pedersen::Params<G2>);
+    /// Commitment params of the verifier params
+    fn vp_commitment_params(
+        vp: &Self::VerifierParam,
+    ) -> (&pedersen::Params<G1>, &pedersen::Params<G2>);
+
+    /// Initial state of the IVC proof
+    fn z_0(proof: &Self::IVCProof) -> &[Fr];
+    /// Last state of the IVC proof
+    fn z_i(proof: &Self::IVCProof) -> &[Fr];
+}
+
+impl Backend for NovaFS {
+    const SCHEME: Scheme = Scheme::Nova;
+
+    fn preprocessor_param(
+        poseidon_config: PoseidonConfig<Fr>,
+        f_circuit: FC,
+    ) -> Self::PreprocessorParam {
+        nova::PreprocessorParam::new(poseidon_config, f_circuit)
+    }
+    fn pp_commitment_params(
+        pp: &Self::ProverParam,
+    ) -> (&pedersen::Params<G1>, &pedersen::Params<G2>) {
+        (&pp.cs_pp, &pp.cf_cs_pp)
+    }
+    fn pp_commitment_params_mut(
+        pp: &mut Self::ProverParam,
+    ) -> (&mut pedersen::Params<G1>, &mut pedersen::Params<G2>) {
+        (&mut pp.cs_pp, &mut pp.cf_cs_pp)
+    }
+    fn vp_commitment_params(
+        vp: &Self::VerifierParam,
+    ) -> (&pedersen::Params<G1>, &pedersen::Params<G2>) {
+        (&vp.cs_vp, &vp.cf_cs_vp)
+    }
+    fn z_0(proof: &Self::IVCProof) -> &[Fr] {
+        &proof.z_0
+    }
+    fn z_i(proof: &Self::IVCProof) -> &[Fr] {
+        &proof.z_i
+    }
+}
+
+#[cfg(feature = "hypernova")]
+impl Backend for HyperNovaFS {
+    const SCHEME: Scheme = Scheme::HyperNova;
+
+    fn preprocessor_param(
+        poseidon_config: PoseidonConfig<Fr>,
+        f_circuit: FC,
+    ) -> Self::PreprocessorParam {
+        hypernova::PreprocessorParam::new(poseidon_config, f_circuit)
+    }
+    fn pp_commitment_params(
+        pp: &Self::ProverParam,
+    ) -> (&pedersen::Params<G1>, &pedersen::Params<G2>) {
+        (&pp.cs_pp, &pp.cf_cs_pp)
+    }
+    fn pp_commitment_params_mut(
+        pp: &mut Self::ProverParam,
+    ) -> (&mut pedersen::Params<G1>, &mut pedersen::Params<G2>) {
+        (&mut pp.cs_pp, &mut pp.cf_cs_pp)
+    }
+    fn vp_commitment_params(
+        vp: &Self::VerifierParam,
+    ) -> (&pedersen::Params<G1>, &pedersen::Params<G2>) {
+        (&vp.cs_vp, &vp.cf_cs_vp)
+    }
+    fn z_0(proof: &Self::IVCProof) -> &[Fr] {
+        &proof.z_0
+    }
+    fn z_i(proof: &Self::IVCProof) -> &[Fr] {
+        &proof.z_i
+    }
+}
+
+#[cfg(feature = "protogalaxy")]
+impl Backend for ProtoGalaxyFS {
+    const SCHEME: Scheme = Scheme::ProtoGalaxy;
+
+    fn preprocessor_param(
+        poseidon_config: PoseidonConfig<Fr>,
+        f_circuit: FC,
+    ) -> Self::PreprocessorParam {
+        (poseidon_config, f_circuit)
+    }
+    fn pp_commitment_params(
+        pp: &Self::ProverParam,
+    ) -> (&pedersen::Params<G1>, &pedersen::Params<G2>) {
+        (&pp.cs_params, &pp.cf_cs_params)
+    }
+    fn pp_commitment_params_mut(
+        pp: &mut Self::ProverParam,
+    ) -> (&mut pedersen::Params<G1>, &mut pedersen::Params<G2>) {
+        (&mut pp.cs_params, &mut pp.cf_cs_params)
+    }
+    fn vp_commitment_params(
+        vp: &Self::VerifierParam,
+    ) -> (&pedersen::Params<G1>, &pedersen::Params<G2>) {
+        (&vp.cs_vp, &vp.cf_cs_vp)
+    }
+    fn z_0(proof: &Self::IVCProof) -> &[Fr] {
+        &proof.z_0
+    }
+    fn z_i(proof: &Self::IVCProof) -> &[Fr] {
+        &proof.z_i
+    }
+}
+
+/// Evaluates the given expression with `$B` being the `Backend` type of the given scheme.
+macro_rules! with_backend {
+    ($scheme:expr, $B:ident => $body:expr) => {
+        match $scheme {
+            Scheme::Nova => {
+                type $B = NovaFS;
+                $body
+            }
+            #[cfg(feature = "hypernova")]
+            Scheme::HyperNova => {
+                type $B = HyperNovaFS;
+                $body
+            }
+            #[cfg(feature = "protogalaxy")]
+            Scheme::ProtoGalaxy => {
+                type $B = ProtoGalaxyFS;
+                $body
+            }
+            #[allow(unreachable_patterns)]
+            scheme => Err(EthDosError::Format(format!(
+                "folding scheme {:?} not available in this build",
+                scheme
+            ))),
+        }
+    };
+}
+
+// returns the folding scheme of the serialized artefact
+fn scheme_of(b: &[u8]) -> Result<Scheme, EthDosError> {
+    Ok(format::read_header(b)?.scheme)
+}
+
+// returns the id of the serialized params, checking that the given artefacts were generated with
+// the same params
+fn check_params(params: &[u8], artefacts: &[&[u8]]) -> Result<[u8; 32], EthDosError> {
+    let id = format::read_header(params)?.params_id;
+    for b in artefacts {
+        if format::read_header(b)?.params_id != id {
+            return Err(EthDosError::Format(
+                "params id mismatch, the artefacts were generated with different params"
+                    .to_string(),
+            ));
+        }
+    }
+    Ok(id)
+}
+
+/// Returns the id of the params of the serialized artefact (see `format::params_id`), recomputed
+/// from the content of the params, and taken from the header of the proofs.
+pub fn params_id(b: &[u8]) -> Result<[u8; 32], EthDosError> {
+    let header = format::read_header(b)?;
+    let domain = api::artefact_domain(b)?;
+    let id = match header.kind {
+        #[cfg(feature = "prover")]
+        Kind::ProverParams => with_backend!(header.scheme, B => {
+            api::prover_params_id::<B>(&api::deserialize_prover_params::<B>(b, domain)?, domain)
+        })?,
+        Kind::VerifierParams => with_backend!(header.scheme, B => {
+            api::verifier_params_id::<B>(&api::deserialize_verifier_params::<B>(b, domain)?, domain)
+        })?,
+        #[cfg(not(feature = "prover"))]
+        Kind::ProverParams => {
+            return Err(EthDosError::Format(
+                "prover params not available in this build".to_string(),
+            ))
+        }
+        Kind::IVCProof | Kind::CompressedProof => return Ok(header.params_id),
+        #[cfg(feature = "decider")]
+        Kind::DeciderProverParams | Kind::DeciderVerifierParams => return decider::params_id(b),
+        kind => {
+            return Err(EthDosError::Format(format!(
+                "{:?} do not depend on the params",
+                kind
+            )))
+        }
+    };
+    if id != header.params_id {
+        return Err(EthDosError::Format(
+            "params id mismatch, the params do not match the id of their header".to_string(),
+        ));
+    }
+    Ok(id)
+}
+
+/// Generates the params for the given folding scheme and domain, returns the serialized prover and
+/// verifier params.
+#[cfg(feature = "prover")]
+pub fn setup(scheme: Scheme, domain: Fr) -> Result<(Vec<u8>, Vec<u8>), EthDosError> {
+    setup_with_rng(scheme, domain, &mut OsRng)
+}
+
+/// Same as `setup`, with the given source of randomness (see `api::seeded_rng`).
+#[cfg(feature = "prover")]
+pub fn setup_with_rng<R: CryptoRngCore>(
+    scheme: Scheme,
+    domain: Fr,
+    rng: &mut R,
+) -> Result<(Vec<u8>, Vec<u8>), EthDosError> {
+    with_backend!(scheme, B => {
+        let (pp, vp) = api::gen_params_with_rng::<B, _>(rng, domain)?;
+        Ok((
+            api::serialize_prover_params::<B>(&pp, domain)?,
+            api::serialize_verifier_params::<B>(&vp, domain)?,
+        ))
+    })
+}
+
+/// Generates the canonical params for the given folding scheme and domain, see
+/// `api::gen_canonical_params`.
+#[cfg(feature = "prover")]
+pub fn setup_canonical(scheme: Scheme, domain: Fr) -> Result<(Vec<u8>, Vec<u8>), EthDosError> {
+    with_backend!(scheme, B => {
+        let (pp, vp) = api::gen_canonical_params::<B>(domain)?;
+        Ok((
+            api::serialize_prover_params::<B>(&pp, domain)?,
+            api::serialize_verifier_params::<B>(&vp, domain)?,
+        ))
+    })
+}
+
+/// Derives the serialized verifier params from the serialized prover params, see
+/// `api::verifier_params_from_prover_params`.
+#[cfg(feature = "prover")]
+pub fn verifier_params(pp: &[u8]) -> Result<Vec<u8>, EthDosError> {
+    let domain = api::artefact_domain(pp)?;
+    with_backend!(scheme_of(pp)?, B => {
+        let pp = api::deserialize_prover_params::<B>(pp, domain)?;
+        let vp = api::verifier_params_from_prover_params::<B>(&pp, domain)?;
+        api::serialize_verifier_params::<B>(&vp, domain)
+    })
+}
+
+/// Folds the chain of signatures with the folding scheme of the given params, returns the
+/// serialized IVC proof.
+#[cfg(feature = "prover")]
+pub fn fold(
+    pp: &[u8],
+    vp: &[u8],
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+) -> Result<Vec<u8>, EthDosError> {
+    fold_with_rng(pp, vp, pks_sigs, revocations, &mut OsRng)
+}
+
+/// Same as `fold`, with the given source of randomness (see `api::seeded_rng`).
+#[cfg(feature = "prover")]
+pub fn fold_with_rng<R: CryptoRngCore>(
+    pp: &[u8],
+    vp: &[u8],
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+    rng: &mut R,
+) -> Result<Vec<u8>, EthDosError> {
+    fold_with_progress(
+        pp,
+        vp,
+        pks_sigs,
+        revocations,
+        rng,
+        |_| {},
+        &CancellationToken::new(),
+    )
+}
+
+/// Same as `fold_with_rng`, reporting the progress after each step and stopping once `cancel` is
+/// cancelled, see `api::fold_sigs_with_progress`.
+#[cfg(feature = "prover")]
+pub fn fold_with_progress<R: CryptoRngCore, P: FnMut(FoldProgress)>(
+    pp: &[u8],
+    vp: &[u8],
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+    rng: &mut R,
+    progress: P,
+    cancel: &CancellationToken,
+) -> Result<Vec<u8>, EthDosError> {
+    let domain = api::artefact_domain(pp)?;
+    let params_id = check_params(vp, &[pp])?;
+    with_backend!(scheme_of(pp)?, B => {
+        let params = (
+            api::deserialize_prover_params::<B>(pp, domain)?,
+            api::deserialize_verifier_params::<B>(vp, domain)?,
+        );
+        let proof = api::fold_sigs_with_progress::<B, _, _>(
+            rng,
+            &params,
+            domain,
+            pks_sigs,
+            revocations,
+            progress,
+            cancel,
+        )?;
+        api::serialize_ivc_proof::<B>(&proof, domain, params_id)
+    })
+}
+
+/// Folds the given chains of signatures concurrently, returns the serialized IVC proof of each
+/// chain, see `api::fold_batch`.
+#[cfg(feature = "parallel")]
+pub fn fold_batch<P: Fn(BatchProgress) + Sync>(
+    pp: &[u8],
+    vp: &[u8],
+    chains: &[Vec<SigPk<EdwardsProjective>>],
+    revocations: &RevocationTree,
+    n_threads: Option<usize>,
+    progress: P,
+) -> Result<Vec<Result<Vec<u8>, EthDosError>>, EthDosError> {
+    let domain = api::artefact_domain(pp)?;
+    let params_id = check_params(vp, &[pp])?;
+    with_backend!(scheme_of(pp)?, B => {
+        let params = (
+            api::deserialize_prover_params::<B>(pp, domain)?,
+            api::deserialize_verifier_params::<B>(vp, domain)?,
+        );
+        api::par_chains(chains, n_threads, progress, |pks_sigs| {
+            let proof = api::fold_sigs::<B>(&params, domain, pks_sigs, revocations)?;
+            api::serialize_ivc_proof::<B>(&proof, domain, params_id)
+        })
+    })
+}
+
+/// Extends the serialized IVC proof by one step, see `api::extend_proof`.
+#[cfg(feature = "prover")]
+pub fn extend(
+    pp: &[u8],
+    vp: &[u8],
+    proof: &[u8],
+    sig_pk: SigPk<EdwardsProjective>,
+    revocations: &RevocationTree,
+) -> Result<Vec<u8>, EthDosError> {
+    let domain = api::artefact_domain(pp)?;
+    let params_id = check_params(vp, &[pp, proof])?;
+    with_backend!(scheme_of(pp)?, B => {
+        let params = (
+            api::deserialize_prover_params::<B>(pp, domain)?,
+            api::deserialize_verifier_params::<B>(vp, domain)?,
+        );
+        let proof = api::deserialize_ivc_proof::<B>(proof, domain)?;
+        let proof = api::extend_proof::<B>(params, domain, proof, sig_pk, revocations)?;
+        api::serialize_ivc_proof::<B>(&proof, domain, params_id)
+    })
+}
+
+/// Verifies the serialized IVC proof, returns its public statement.
+pub fn verify(vp: &[u8], proof: &[u8]) -> Result<ProofState, EthDosError> {
+    let domain = api::artefact_domain(vp)?;
+    check_params(vp, &[proof])?;
+    with_backend!(scheme_of(vp)?, B => {
+        let vp = api::deserialize_verifier_params::<B>(vp, domain)?;
+        let proof = api::deserialize_ivc_proof::<B>(proof, domain)?;
+        let state = api::proof_state::<B>(&proof)?;
+        api::verify_proof::<B>(vp, proof)?;
+        Ok(state)
+    })
+}
+
+/// Verifies the serialized IVC proof and its public statement, see `api::verify_proof_state`.
+pub fn verify_state(
+    vp: &[u8],
+    proof: &[u8],
+    expected_pk_0: PublicKey<EdwardsProjective>,
+    revocation_root: Fr,
+    now: u64,
+    expected_pk_i: Option<PublicKey<EdwardsProjective>>,
+    max_degree: Option<u64>,
+) -> Result<ProofState, EthDosError> {
+    let domain = api::artefact_domain(vp)?;
+    check_params(vp, &[proof])?;
+    with_backend!(scheme_of(vp)?, B => {
+        let vp = api::deserialize_verifier_params::<B>(vp, domain)?;
+        let proof = api::deserialize_ivc_proof::<B>(proof, domain)?;
+        api::verify_proof_state::<B>(
+            vp,
+            proof,
+            expected_pk_0,
+            revocation_root,
+            now,
+            expected_pk_i,
+            max_degree,
+        )
+    })
+}
+
+/// Proves the ownership of the last public key of the serialized IVC proof, returns the serialized
+/// ownership signature, see `api::prove_ownership`.
+pub fn prove_ownership(
+    proof: &[u8],
+    sk: &SecretKey,
+    challenge: Fr,
+) -> Result<Vec<u8>, EthDosError> {
+    let domain = api::artefact_domain(proof)?;
+    with_backend!(scheme_of(proof)?, B => {
+        let proof = api::deserialize_ivc_proof::<B>(proof, domain)?;
+        let ownership = api::prove_ownership::<B>(&proof, sk, challenge)?;
+        api::serialize_sig_pk(&ownership, domain)
+    })
+}
+
+/// Verifies the serialized IVC proof together with the serialized ownership signature of its last
+/// public key, see `api::verify_with_ownership`.
+pub fn verify_with_ownership(
+    vp: &[u8],
+    proof: &[u8],
+    ownership: &[u8],
+    challenge: Fr,
+    expected_pk_0: PublicKey<EdwardsProjective>,
+    revocation_root: Fr,
+    now: u64,
+    max_degree: Option<u64>,
+) -> Result<ProofState, EthDosError> {
+    let domain = api::artefact_domain(vp)?;
+    check_params(vp, &[proof])?;
+    let ownership = api::deserialize_sig_pk(ownership, domain)?;
+    with_backend!(scheme_of(vp)?, B => {
+        let vp = api::deserialize_verifier_params::<B>(vp, domain)?;
+        let proof = api::deserialize_ivc_proof::<B>(proof, domain)?;
+        api::verify_with_ownership::<B>(
+            vp,
+            proof,
+            &ownership,
+            challenge,
+            expected_pk_0,
+            revocation_root,
+            now,
+            max_degree,
+        )
+    })
+}
+
+/// Generates the Decider params for the given domain, returns the serialized Decider params
+/// (prover side) and verifier params, see `decider::gen_decider_params`.
+#[cfg(feature = "decider")]
+pub fn setup_decider(domain: Fr) -> Result<(Vec<u8>, Vec<u8>), EthDosError> {
+    setup_decider_with_rng(domain, &mut OsRng)
+}
+
+/// Same as `setup_decider`, with the given source of randomness (see `api::seeded_rng`).
+#[cfg(feature = "decider")]
+pub fn setup_decider_with_rng<R: CryptoRngCore>(
+    domain: Fr,
+    rng: &mut R,
+) -> Result<(Vec<u8>, Vec<u8>), EthDosError> {
+    let params = decider::gen_decider_params_with_rng(rng, domain)?;
+    Ok((
+        decider::serialize_decider_params(&params, domain)?,
+        decider::serialize_decider_verifier_params(&params.2, domain)?,
+    ))
+}
+
+/// Folds the chain of signatures followed by the presentation step with the serialized Decider
+/// params, returns the serialized compressed presentation proof, see `decider::present`.
+#[cfg(feature = "decider")]
+pub fn present(
+    decider_params: &[u8],
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+    sk: &SecretKey,
+    challenge: Fr,
+    max_degree: u64,
+) -> Result<Vec<u8>, EthDosError> {
+    let domain = api::artefact_domain(decider_params)?;
+    let params_id = format::read_header(decider_params)?.params_id;
+    let (fs_params, decider_pp, _) = decider::deserialize_decider_params(decider_params, domain)?;
+    let proof = decider::present(
+        &fs_params,
+        decider_pp,
+        domain,
+        pks_sigs,
+        revocations,
+        sk,
+        challenge,
+        max_degree,
+    )?;
+    decider::serialize_compressed_proof(&proof, domain, params_id)
+}
+
+/// Verifies the serialized presentation proof, see `decider::verify_presentation`.
+#[cfg(feature = "decider")]
+pub fn verify_presentation(
+    decider_vp: &[u8],
+    proof: &[u8],
+    expected_pk_0: PublicKey<EdwardsProjective>,
+    revocation_root: Fr,
+    now: u64,
+    challenge: Fr,
+    max_degree: u64,
+) -> Result<Presentation, EthDosError> {
+    let domain = api::artefact_domain(decider_vp)?;
+    let params_id = format::read_header(decider_vp)?.params_id;
+    let decider_vp = decider::deserialize_decider_verifier_params(decider_vp, domain)?;
+    let proof = decider::deserialize_compressed_proof(proof, domain, params_id)?;
+    decider::verify_presentation(
+        decider_vp,
+        &proof,
+        expected_pk_0,
+        revocation_root,
+        now,
+        challenge,
+        max_degree,
+    )
+}
+
+/// Returns the public statement of the serialized IVC proof, without verifying it.
+pub fn inspect(proof: &[u8]) -> Result<ProofState, EthDosError> {
+    let domain = api::artefact_domain(proof)?;
+    with_backend!(scheme_of(proof)?, B => {
+        api::proof_state::<B>(&api::deserialize_ivc_proof::<B>(proof, domain)?)
+    })
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use ark_serialize::{CanonicalSerialize, Compress, Validate};
+    use rand::rngs::OsRng;
+
+    use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+
+    use super::*;
+    use crate::signature::{domain_tag, gen_signatures};
+    use crate::utils::{dbg, elapsed, get_time};
+
+    // runs the EthDosCircuit through the given folding scheme: fold, extend, verify & serialization
+    fn test_backend<B: Backend>() {
+        const N_STEPS: usize = 2;
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let domain = api::default_domain();
+        let sigs = gen_signatures::<OsRng, EdwardsProjective>(
+            &mut OsRng,
+            &poseidon_config,
+            domain,
+            N_STEPS + 1,
+        )
+        .unwrap();
+
+        let start = get_time();
+        let revocations = RevocationTree::new(&poseidon_config);
+        let params = api::gen_params::<B>(domain).unwrap();
+        let ivc_proof =
+            api::fold_sigs::<B>(&params, domain, &sigs[..N_STEPS], &revocations).unwrap();
+        let ivc_proof = api::extend_proof::<B>(
+            params.clone(),
+            domain,
+            ivc_proof,
+            sigs[N_STEPS],
+            &revocations,
+        )
+        .unwrap();
+        dbg(format!(
+            "{:?} params, {} steps and extend: {:?}ms",
+            B::SCHEME,
+            N_STEPS,
+            elapsed(start)
+        ));
+        assert_eq!(B::z_i(&ivc_proof)[4], Fr::from((N_STEPS + 1) as u32));
+
+        let params_id = api::verifier_params_id::<B>(&params.1, domain).unwrap();
+        let b = api::serialize_ivc_proof::<B>(&ivc_proof, domain, params_id).unwrap();
+        let ivc_proof = api::deserialize_ivc_proof::<B>(&b, domain).unwrap();
+        let state = api::verify_proof_state::<B>(
+            params.1.clone(),
+            ivc_proof,
+            sigs[0].pk,
+            revocations.root(),
+            get_time() / 1000,
+            Some(sigs[N_STEPS].pk),
+            Some((N_STEPS + 1) as u64),
+        )
+        .unwrap();
+        assert_eq!(state.degree, (N_STEPS + 1) as u64);
+
+        // the serialized params are tagged with the folding scheme and the domain, and truncated
+        // ones rejected
+        let vp = api::serialize_verifier_params::<B>(&params.1, domain).unwrap();
+        assert_eq!(format::read_header(&vp).unwrap().scheme, B::SCHEME);
+        assert_eq!(api::artefact_domain(&vp).unwrap(), domain);
+        assert!(api::deserialize_verifier_params::<B>(&vp[..vp.len() / 2], domain).is_err());
+
+        // the verifier params can be derived from the prover params
+        let pp = api::serialize_prover_params::<B>(&params.0, domain).unwrap();
+        assert_eq!(verifier_params(&pp).unwrap(), vp);
+    }
+
+    #[test]
+    fn test_nova() {
+        test_backend::<NovaFS>();
+    }
+
+    #[cfg(feature = "hypernova")]
+    #[test]
+    fn test_hypernova() {
+        test_backend::<HyperNovaFS>();
+    }
+
+    #[cfg(feature = "protogalaxy")]
+    #[test]
+    fn test_protogalaxy() {
+        test_backend::<ProtoGalaxyFS>();
+    }
+
+    // test the runtime-dispatched methods, and that artefacts of different folding schemes or
+    // domains can not be mixed
+    #[test]
+    fn test_runtime_dispatch() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let domain = api::default_domain();
+        let sigs =
+            gen_signatures::<OsRng, EdwardsProjective>(&mut OsRng, &poseidon_config, domain, 2)
+                .unwrap();
+
+        let revocations = RevocationTree::new(&poseidon_config);
+        let (pp, vp) = setup(Scheme::Nova, domain).unwrap();
+        let proof = fold(&pp, &vp, &sigs[..1], &revocations).unwrap();
+        let proof = extend(&pp, &vp, &proof, sigs[1], &revocations).unwrap();
+        let state = verify(&vp, &proof).unwrap();
+        assert_eq!(state, inspect(&proof).unwrap());
+
+        // the batch API returns the serialized proof of each chain
+        #[cfg(feature = "parallel")]
+        {
+            let proofs = fold_batch(&pp, &vp, &[sigs.clone()], &revocations, Some(1), |_| {});
+            assert_eq!(
+                verify(&vp, proofs.unwrap()[0].as_ref().unwrap()).unwrap(),
+                state
+            );
+        }
+        verify_state(
+            &vp,
+            &proof,
+            sigs[0].pk,
+            revocations.root(),
+            get_time() / 1000,
+            Some(sigs[1].pk),
+            Some(2),
+        )
+        .unwrap();
+
+        assert!(setup(Scheme::Unspecified, domain).is_err());
+
+        // the proof carries the id of its params, and is rejected with other params of the same
+        // domain
+        let id = params_id(&vp).unwrap();
+        assert_eq!(params_id(&pp).unwrap(), id);
+        assert_eq!(params_id(&proof).unwrap(), id);
+        let (pp_session, vp_session) = setup(Scheme::Nova, domain).unwrap();
+        assert!(matches!(
+            verify(&vp_session, &proof),
+            Err(EthDosError::Format(_))
+        ));
+        assert!(extend(&pp_session, &vp_session, &proof, sigs[1], &revocations).is_err());
+        assert!(fold(&pp, &vp_session, &sigs[..1], &revocations).is_err());
+        // params whose header does not match their content are rejected
+        let mut vp_tampered = vp.clone();
+        vp_tampered[format::HEADER_LEN - 1] ^= 1;
+        assert!(params_id(&vp_tampered).is_err());
+
+        // the proof can not be verified with the params of another domain
+        let (_, vp_other) = setup(Scheme::Nova, domain_tag("other")).unwrap();
+        assert!(matches!(
+            verify(&vp_other, &proof),
+            Err(EthDosError::Format(_))
+        ));
+
+        #[cfg(feature = "protogalaxy")]
+        {
+            let (_, vp_pg) = setup(Scheme::ProtoGalaxy, domain).unwrap();
+            assert!(matches!(
+                verify(&vp_pg, &proof),
+                Err(EthDosError::Format(_))
+            ));
+        }
+    }
+}
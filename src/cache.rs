@@ -0,0 +1,202 @@
+//! This file contains the on-disk cache of the serialized params, keyed by their id (see
+//! `format::params_id`), so that the provers and verifiers of different processes use the same
+//! params without running the setup again. The cache directory contains:
+//!
+//! - `<id>.pp` and `<id>.vp`: the prover and verifier params of the given id
+//! - `<key>.id`: the id of the canonical params of the given key (see `canonical_key`)
+//!
+//! where the ids and keys are hex encoded, and the params are stored as they are serialized. The
+//! entries are plain bytes under string keys, so the same layout can be used on top of IndexedDB
+//! by the WASM bindings (see `params_id` and `canonical_params_key` at `lib.rs`).
+use ark_bn254::Fr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::api;
+use crate::backend;
+use crate::error::EthDosError;
+use crate::format::{self, Kind, Scheme};
+
+const PROVER_PARAMS_EXT: &str = "pp";
+const VERIFIER_PARAMS_EXT: &str = "vp";
+const CANONICAL_EXT: &str = "id";
+
+/// Hex encoding of the params id, used as key of the cache entries
+pub fn id_to_hex(id: &[u8; 32]) -> String {
+    id.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Inverse of `id_to_hex`
+pub fn id_from_hex(s: &str) -> Result<[u8; 32], EthDosError> {
+    let s = s.trim();
+    if s.len() != 64 || !s.is_ascii() {
+        return Err(EthDosError::Format(format!("invalid params id '{}'", s)));
+    }
+    let mut id = [0u8; 32];
+    for (i, b) in id.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&s[2 * i..2 * i + 2], 16)
+            .map_err(|_| EthDosError::Format(format!("invalid params id '{}'", s)))?;
+    }
+    Ok(id)
+}
+
+/// Key under which the id of the canonical params of the given folding scheme and domain is
+/// stored. It only depends on the circuit config, so it is known before generating the params.
+pub fn canonical_key(scheme: Scheme, domain: Fr) -> Result<[u8; 32], EthDosError> {
+    Ok(format::canonical_seed(
+        api::circuit_digest()?,
+        scheme,
+        api::domain_bytes(domain),
+    ))
+}
+
+/// On-disk cache of the params. The directory is trusted: the params read from it are only
+/// checked to have the expected id in their header, while the ones added are checked to match
+/// their id.
+pub struct ParamsCache {
+    dir: PathBuf,
+}
+
+impl ParamsCache {
+    /// Opens the cache at the given directory, creating it if it does not exist
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self, EthDosError> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    /// Adds the serialized verifier params, and the prover params if given, returns their id.
+    pub fn put(&self, pp: Option<&[u8]>, vp: &[u8]) -> Result<[u8; 32], EthDosError> {
+        if format::read_header(vp)?.kind != Kind::VerifierParams {
+            return Err(EthDosError::Format("expected verifier params".to_string()));
+        }
+        let id = backend::params_id(vp)?;
+        if let Some(pp) = pp {
+            let header = format::read_header(pp)?;
+            if header.kind != Kind::ProverParams || header.params_id != id {
+                return Err(EthDosError::Format(
+                    "the prover params do not match the verifier params".to_string(),
+                ));
+            }
+            self.write(&id_to_hex(&id), PROVER_PARAMS_EXT, pp)?;
+        }
+        self.write(&id_to_hex(&id), VERIFIER_PARAMS_EXT, vp)?;
+        Ok(id)
+    }
+
+    /// Returns the serialized prover params of the given id, if they are in the cache
+    pub fn prover_params(&self, id: &[u8; 32]) -> Result<Option<Vec<u8>>, EthDosError> {
+        self.read_params(id, PROVER_PARAMS_EXT)
+    }
+
+    /// Returns the serialized verifier params of the given id, if they are in the cache
+    pub fn verifier_params(&self, id: &[u8; 32]) -> Result<Option<Vec<u8>>, EthDosError> {
+        self.read_params(id, VERIFIER_PARAMS_EXT)
+    }
+
+    /// Returns the serialized verifier params with which the serialized proof was generated, if
+    /// they are in the cache
+    pub fn verifier_params_of(&self, proof: &[u8]) -> Result<Option<Vec<u8>>, EthDosError> {
+        self.verifier_params(&backend::params_id(proof)?)
+    }
+
+    /// Returns the serialized canonical prover and verifier params of the given folding scheme
+    /// and domain (see `api::gen_canonical_params`), generating and adding them to the cache if
+    /// they are not in it.
+    #[cfg(feature = "prover")]
+    pub fn canonical(&self, scheme: Scheme, domain: Fr) -> Result<(Vec<u8>, Vec<u8>), EthDosError> {
+        let key = id_to_hex(&canonical_key(scheme, domain)?);
+        if let Some(id) = self.read(&key, CANONICAL_EXT)? {
+            let id = id_from_hex(&String::from_utf8_lossy(&id))?;
+            if let (Some(pp), Some(vp)) = (self.prover_params(&id)?, self.verifier_params(&id)?) {
+                return Ok((pp, vp));
+            }
+        }
+        let (pp, vp) = backend::setup_canonical(scheme, domain)?;
+        let id = self.put(Some(&pp), &vp)?;
+        self.write(&key, CANONICAL_EXT, id_to_hex(&id).as_bytes())?;
+        Ok((pp, vp))
+    }
+
+    fn path(&self, name: &str, ext: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", name, ext))
+    }
+
+    fn read(&self, name: &str, ext: &str) -> Result<Option<Vec<u8>>, EthDosError> {
+        match fs::read(self.path(name, ext)) {
+            Ok(b) => Ok(Some(b)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn read_params(&self, id: &[u8; 32], ext: &str) -> Result<Option<Vec<u8>>, EthDosError> {
+        let b = match self.read(&id_to_hex(id), ext)? {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+        if format::read_header(&b)?.params_id != *id {
+            return Err(EthDosError::Format(format!(
+                "the cache entry {}.{} does not match its id",
+                id_to_hex(id),
+                ext
+            )));
+        }
+        Ok(Some(b))
+    }
+
+    // writes to a temporary file first, so that the processes sharing the cache never read a
+    // partially written entry
+    fn write(&self, name: &str, ext: &str, b: &[u8]) -> Result<(), EthDosError> {
+        let path = self.path(name, ext);
+        let tmp = self
+            .dir
+            .join(format!("{}.{}.tmp-{}", name, ext, std::process::id()));
+        fs::write(&tmp, b)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params_cache() {
+        let dir = std::env::temp_dir().join(format!("ethdos-fold-cache-{}", std::process::id()));
+        let cache = ParamsCache::new(&dir).unwrap();
+        let domain = api::default_domain();
+
+        let id = [42u8; 32];
+        assert_eq!(id_from_hex(&id_to_hex(&id)).unwrap(), id);
+        assert!(id_from_hex("00").is_err());
+        assert_eq!(cache.verifier_params(&id).unwrap(), None);
+
+        // the canonical params are generated once, and then read from the cache, also by a cache
+        // opened by another process at the same directory
+        let (pp, vp) = cache.canonical(Scheme::Nova, domain).unwrap();
+        let id = backend::params_id(&vp).unwrap();
+        assert_eq!(cache.prover_params(&id).unwrap(), Some(pp.clone()));
+        let other = ParamsCache::new(&dir).unwrap();
+        assert_eq!(
+            other.canonical(Scheme::Nova, domain).unwrap(),
+            (pp.clone(), vp.clone())
+        );
+
+        // random params are added under their own id, and mismatching params rejected
+        let (pp_random, vp_random) = backend::setup(Scheme::Nova, domain).unwrap();
+        assert!(cache.put(Some(&pp_random), &vp).is_err());
+        assert!(cache.put(None, &pp_random).is_err());
+        let id_random = cache.put(Some(&pp_random), &vp_random).unwrap();
+        assert_ne!(id_random, id);
+        assert_eq!(cache.verifier_params(&id_random).unwrap(), Some(vp_random));
+
+        // a tampered entry is rejected
+        fs::write(cache.path(&id_to_hex(&id), VERIFIER_PARAMS_EXT), &pp_random).unwrap();
+        assert!(cache.verifier_params(&id).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
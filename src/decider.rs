@@ -0,0 +1,769 @@
+//! This file contains the 'compress' mode, which folds the signatures with Nova (using KZG
+//! commitments over BN254) and then runs Sonobe's Nova Decider to obtain a constant-size proof
+//! (Groth16 + KZG), which can be verified onchain with the generated Solidity verifier. The
+//! presentations (see `present`) are always compressed, so that they do not contain the witnesses
+//! of the folding.
+use ark_bn254::{Bn254, Fr, G1Projective as G1};
+use ark_groth16::Groth16;
+use ark_grumpkin::Projective as G2;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use rand::rngs::OsRng;
+use rand_core::CryptoRngCore;
+
+use arkeddsa::ed_on_bn254_twist::EdwardsProjective;
+use arkeddsa::PublicKey;
+
+use folding_schemes::{
+    commitment::{kzg::KZG, pedersen::Pedersen},
+    folding::{
+        nova::{
+            decider_eth::{prepare_calldata, Decider as DeciderEth},
+            CommittedInstance, Nova, PreprocessorParam,
+        },
+        traits::CommittedInstanceOps,
+    },
+    frontend::FCircuit,
+    transcript::poseidon::poseidon_canonical_config,
+    Decider, FoldingScheme,
+};
+use solidity_verifiers::{
+    utils::get_function_selector_for_nova_cyclefold_verifier,
+    verifiers::nova_cyclefold::get_decider_template_for_cyclefold_decider,
+    NovaCycleFoldVerifierKey,
+};
+
+use crate::api::{
+    self, circuit_digest, circuit_params, default_domain, domain_bytes, CancellationToken,
+    FoldProgress, Presentation, ProofState, FC,
+};
+use crate::error::EthDosError;
+use crate::fcircuit::EthDosStep;
+use crate::format::{self, Kind, Scheme};
+use crate::keys::{self, SecretKey};
+use crate::revocation::RevocationTree;
+use crate::signature::SigPk;
+use crate::utils::{dbg, elapsed, get_time};
+
+// the Decider needs Nova to use KZG commitments over the first curve of the cycle
+pub type DeciderFS = Nova<G1, G2, FC, KZG<'static, Bn254>, Pedersen<G2>, false>;
+pub type D = DeciderEth<G1, G2, FC, KZG<'static, Bn254>, Pedersen<G2>, Groth16<Bn254>, DeciderFS>;
+
+pub type DeciderFSParams = (
+    <DeciderFS as FoldingScheme<G1, G2, FC>>::ProverParam,
+    <DeciderFS as FoldingScheme<G1, G2, FC>>::VerifierParam,
+);
+pub type DeciderProverParams = <D as Decider<G1, G2, FC, DeciderFS>>::ProverParam;
+pub type DeciderVerifierParams = <D as Decider<G1, G2, FC, DeciderFS>>::VerifierParam;
+pub type DeciderProof = <D as Decider<G1, G2, FC, DeciderFS>>::Proof;
+/// Params of the prover in 'compress' mode, which include the Decider verifier params, from which
+/// their id is computed (see `decider_params_id`)
+pub type DeciderParams = (DeciderFSParams, DeciderProverParams, DeciderVerifierParams);
+
+/// Constant-size proof, together with the public inputs needed to verify it. The number of steps
+/// `i` is the degree of the chain, or `max_degree + 1` for the presentations, which are padded.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CompressedProof {
+    pub i: Fr,
+    pub z_0: Vec<Fr>,
+    pub z_i: Vec<Fr>,
+    pub U_i: CommittedInstance<G1>,
+    pub u_i: CommittedInstance<G1>,
+    pub proof: DeciderProof,
+}
+
+/// Generates the params for folding in 'compress' mode (Nova with KZG) and the Decider params, for
+/// the vouches of the given domain.
+pub fn gen_decider_params(domain: Fr) -> Result<DeciderParams, EthDosError> {
+    gen_decider_params_with_rng(&mut OsRng, domain)
+}
+
+/// Same as `gen_decider_params`, with the given source of randomness (see `api::seeded_rng`).
+pub fn gen_decider_params_with_rng<R: CryptoRngCore>(
+    rng: &mut R,
+    domain: Fr,
+) -> Result<DeciderParams, EthDosError> {
+    let poseidon_config = poseidon_canonical_config::<Fr>();
+
+    let f_circuit = FC::new(circuit_params(domain))?;
+
+    let nova_preprocess_params = PreprocessorParam::new(poseidon_config, f_circuit.clone());
+    let start = get_time();
+    let nova_params = DeciderFS::preprocess(&mut *rng, &nova_preprocess_params)?;
+    dbg(format!(
+        "Nova (KZG) params generated: {:?}ms",
+        elapsed(start)
+    ));
+
+    let start = get_time();
+    let (decider_pp, decider_vp) =
+        D::preprocess(&mut *rng, (nova_params.clone(), f_circuit.state_len()))?;
+    dbg(format!("Decider params generated: {:?}ms", elapsed(start)));
+
+    Ok((nova_params, decider_pp, decider_vp))
+}
+
+/// Folds the given chain of signatures (as in `api::fold_sigs`) and compresses the resulting IVC
+/// proof into a Decider proof.
+pub fn fold_sigs_compressed(
+    fs_params: &DeciderFSParams,
+    decider_pp: DeciderProverParams,
+    domain: Fr,
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+) -> Result<CompressedProof, EthDosError> {
+    fold_sigs_compressed_with_rng(
+        &mut OsRng,
+        fs_params,
+        decider_pp,
+        domain,
+        pks_sigs,
+        revocations,
+    )
+}
+
+/// Same as `fold_sigs_compressed`, with the given source of randomness (see `api::seeded_rng`).
+pub fn fold_sigs_compressed_with_rng<R: CryptoRngCore>(
+    rng: &mut R,
+    fs_params: &DeciderFSParams,
+    decider_pp: DeciderProverParams,
+    domain: Fr,
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+) -> Result<CompressedProof, EthDosError> {
+    fold_sigs_compressed_with_progress(
+        rng,
+        fs_params,
+        decider_pp,
+        domain,
+        pks_sigs,
+        revocations,
+        |_| {},
+        &CancellationToken::new(),
+    )
+}
+
+/// Same as `fold_sigs_compressed_with_rng`, reporting the progress of the folding steps and
+/// stopping once `cancel` is cancelled, as `api::fold_sigs_with_progress`.
+#[allow(clippy::too_many_arguments)]
+pub fn fold_sigs_compressed_with_progress<R: CryptoRngCore, P: FnMut(FoldProgress)>(
+    rng: &mut R,
+    fs_params: &DeciderFSParams,
+    decider_pp: DeciderProverParams,
+    domain: Fr,
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+    progress: P,
+    cancel: &CancellationToken,
+) -> Result<CompressedProof, EthDosError> {
+    compress(
+        rng,
+        fs_params,
+        decider_pp,
+        domain,
+        pks_sigs,
+        revocations,
+        None,
+        progress,
+        cancel,
+    )
+}
+
+/// Folds the given chain of signatures followed by the presentation step, in which the owner of
+/// its last public key `pk_i` signs the verifier's `challenge`, and `pk_i` is replaced in the state
+/// by a nullifier and the degree by the verifier's `max_degree`. The presentation is padded to
+/// `max_degree + 1` steps, so the degree can not be greater than `max_degree`. The folding is
+/// compressed into a Decider proof, see the README for what its public inputs reveal.
+#[allow(clippy::too_many_arguments)]
+pub fn present(
+    fs_params: &DeciderFSParams,
+    decider_pp: DeciderProverParams,
+    domain: Fr,
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+    sk: &SecretKey,
+    challenge: Fr,
+    max_degree: u64,
+) -> Result<CompressedProof, EthDosError> {
+    present_with_rng(
+        &mut OsRng,
+        fs_params,
+        decider_pp,
+        domain,
+        pks_sigs,
+        revocations,
+        sk,
+        challenge,
+        max_degree,
+    )
+}
+
+/// Same as `present`, with the given source of randomness (see `api::seeded_rng`).
+#[allow(clippy::too_many_arguments)]
+pub fn present_with_rng<R: CryptoRngCore>(
+    rng: &mut R,
+    fs_params: &DeciderFSParams,
+    decider_pp: DeciderProverParams,
+    domain: Fr,
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+    sk: &SecretKey,
+    challenge: Fr,
+    max_degree: u64,
+) -> Result<CompressedProof, EthDosError> {
+    compress(
+        rng,
+        fs_params,
+        decider_pp,
+        domain,
+        pks_sigs,
+        revocations,
+        Some((sk, challenge, max_degree)),
+        |_| {},
+        &CancellationToken::new(),
+    )
+}
+
+// folds the chain of signatures, followed by the presentation step if given, and compresses it
+#[allow(clippy::too_many_arguments)]
+fn compress<R: CryptoRngCore, P: FnMut(FoldProgress)>(
+    rng: &mut R,
+    fs_params: &DeciderFSParams,
+    decider_pp: DeciderProverParams,
+    domain: Fr,
+    pks_sigs: &[SigPk<EdwardsProjective>],
+    revocations: &RevocationTree,
+    presentation: Option<(&SecretKey, Fr, u64)>,
+    progress: P,
+    cancel: &CancellationToken,
+) -> Result<CompressedProof, EthDosError> {
+    // check the secret key and the degree before the folding, where the degree is the number of
+    // vouch steps
+    let degree = pks_sigs.len() as u64;
+    let presentation = presentation
+        .map(|(sk, challenge, max_degree)| {
+            let sig_pk = keys::sign_challenge(sk, challenge)?;
+            if Some(&sig_pk.pk) != pks_sigs.last().map(|s| &s.pk) {
+                return Err(EthDosError::Key(
+                    "the secret key does not correspond to the last public key of the chain"
+                        .to_string(),
+                ));
+            }
+            if degree > max_degree {
+                return Err(EthDosError::Verification(format!(
+                    "the degree {} is greater than the max_degree {}",
+                    degree, max_degree
+                )));
+            }
+            Ok((
+                EthDosStep::present(sig_pk, challenge, max_degree),
+                max_degree,
+            ))
+        })
+        .transpose()?;
+
+    let mut nova = api::fold_chain::<DeciderFS, _, _>(
+        rng,
+        fs_params,
+        Scheme::Decider,
+        domain,
+        pks_sigs,
+        revocations,
+        progress,
+        cancel,
+    )?;
+    if let Some((step, max_degree)) = presentation {
+        nova.prove_step(&mut *rng, step, None)?;
+        // pad the presentation to `max_degree + 1` steps, so that `i` does not reveal the degree
+        for _ in degree..max_degree {
+            if cancel.is_cancelled() {
+                return Err(EthDosError::Cancelled);
+            }
+            nova.prove_step(&mut *rng, EthDosStep::pad(), None)?;
+        }
+    }
+    if cancel.is_cancelled() {
+        return Err(EthDosError::Cancelled);
+    }
+
+    let start = get_time();
+    let proof = D::prove(&mut *rng, decider_pp, nova.clone())?;
+    dbg(format!("Decider proof generated: {:?}ms", elapsed(start)));
+
+    Ok(CompressedProof {
+        i: nova.i,
+        z_0: nova.z_0,
+        z_i: nova.z_i,
+        U_i: nova.U_i,
+        u_i: nova.u_i,
+        proof,
+    })
+}
+
+/// Verifies the Decider proof natively. This performs the same checks (including the pairings) as
+/// the Solidity verifier returned by `solidity_verifier`.
+pub fn verify_compressed(
+    decider_vp: DeciderVerifierParams,
+    proof: &CompressedProof,
+) -> Result<(), EthDosError> {
+    let verified = D::verify(
+        decider_vp,
+        proof.i,
+        proof.z_0.clone(),
+        proof.z_i.clone(),
+        &proof.U_i.get_commitments(),
+        &proof.u_i.get_commitments(),
+        &proof.proof,
+    )
+    .map_err(|e| EthDosError::Verification(e.to_string()))?;
+    if !verified {
+        return Err(EthDosError::Verification(
+            "Decider proof not valid".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Verifies the Decider proof and checks its public statement, as `api::verify_proof_state` does
+/// for the IVC proofs: that it starts at `expected_pk_0` and was checked against the revocation
+/// tree of root `revocation_root`, that its chain is valid at `now`, and optionally its `pk_i` and
+/// maximum degree. The presentations are rejected.
+pub fn verify_compressed_state(
+    decider_vp: DeciderVerifierParams,
+    proof: &CompressedProof,
+    expected_pk_0: PublicKey<EdwardsProjective>,
+    revocation_root: Fr,
+    now: u64,
+    expected_pk_i: Option<PublicKey<EdwardsProjective>>,
+    max_degree: Option<u64>,
+) -> Result<ProofState, EthDosError> {
+    let state = api::check_state(
+        &proof.z_0,
+        &proof.z_i,
+        expected_pk_0,
+        revocation_root,
+        now,
+        expected_pk_i,
+        max_degree,
+    )?;
+    verify_compressed(decider_vp, proof)?;
+    Ok(state)
+}
+
+/// Verifies the compressed presentation proof (see `present`), checking that it starts at
+/// `expected_pk_0`, that it was checked against the revocation tree of root `revocation_root`,
+/// that its chain of vouches is valid at `now` (in seconds since the unix epoch), and that it
+/// answers the given challenge and `max_degree`, with a degree of at most `max_degree`. Returns the
+/// presentation, whose nullifier can be used to detect repeated presentations for the same
+/// challenge.
+pub fn verify_presentation(
+    decider_vp: DeciderVerifierParams,
+    proof: &CompressedProof,
+    expected_pk_0: PublicKey<EdwardsProjective>,
+    revocation_root: Fr,
+    now: u64,
+    challenge: Fr,
+    max_degree: u64,
+) -> Result<Presentation, EthDosError> {
+    let presentation = api::check_presentation(
+        &proof.z_0,
+        &proof.z_i,
+        expected_pk_0,
+        revocation_root,
+        now,
+        challenge,
+        max_degree,
+    )?;
+    // the presentations are padded to `max_degree + 1` steps
+    if proof.i != Fr::from(max_degree) + Fr::from(1_u32) {
+        return Err(EthDosError::Verification(
+            "the presentation is not padded to max_degree".to_string(),
+        ));
+    }
+    verify_compressed(decider_vp, proof)?;
+    Ok(presentation)
+}
+
+/// Identifier of the Decider params of the given domain, computed over their verifier params. It
+/// is included in the header of the serialized params and of the proofs generated with them.
+pub fn decider_params_id(
+    decider_vp: &DeciderVerifierParams,
+    domain: Fr,
+) -> Result<[u8; 32], EthDosError> {
+    let mut b = vec![];
+    decider_vp.serialize_compressed(&mut b)?;
+    Ok(format::params_id(
+        circuit_digest()?,
+        Scheme::Decider,
+        domain_bytes(domain),
+        &b,
+    ))
+}
+
+/// Returns the id of the serialized Decider params, recomputed from their content and checked
+/// against the one of their header, see `backend::params_id`.
+pub fn params_id(b: &[u8]) -> Result<[u8; 32], EthDosError> {
+    let header = format::read_header(b)?;
+    let domain = api::artefact_domain(b)?;
+    match header.kind {
+        Kind::DeciderProverParams => {
+            deserialize_decider_params(b, domain)?;
+        }
+        Kind::DeciderVerifierParams => {
+            deserialize_decider_verifier_params(b, domain)?;
+        }
+        kind => {
+            return Err(EthDosError::Format(format!(
+                "{:?} are not Decider params",
+                kind
+            )))
+        }
+    };
+    Ok(header.params_id)
+}
+
+pub fn serialize_decider_params(
+    params: &DeciderParams,
+    domain: Fr,
+) -> Result<Vec<u8>, EthDosError> {
+    let ((nova_pp, nova_vp), decider_pp, decider_vp) = params;
+    let mut b = vec![];
+    nova_pp.serialize_compressed(&mut b)?;
+    nova_vp.serialize_compressed(&mut b)?;
+    decider_pp.serialize_compressed(&mut b)?;
+    decider_vp.serialize_compressed(&mut b)?;
+    Ok(format::seal(
+        Kind::DeciderProverParams,
+        Scheme::Decider,
+        circuit_digest()?,
+        domain_bytes(domain),
+        decider_params_id(decider_vp, domain)?,
+        false,
+        &b,
+    ))
+}
+
+pub fn deserialize_decider_params(b: &[u8], domain: Fr) -> Result<DeciderParams, EthDosError> {
+    let id = format::read_header(b)?.params_id;
+    let b = format::open(
+        Kind::DeciderProverParams,
+        Scheme::Decider,
+        circuit_digest()?,
+        domain_bytes(domain),
+        b,
+    )?;
+    let mut reader = b.as_slice();
+    let nova_pp = DeciderFS::pp_deserialize_with_mode(
+        &mut reader,
+        Compress::Yes,
+        Validate::Yes,
+        circuit_params(domain),
+    )?;
+    let nova_vp = DeciderFS::vp_deserialize_with_mode(
+        &mut reader,
+        Compress::Yes,
+        Validate::Yes,
+        circuit_params(domain),
+    )?;
+    let decider_pp = DeciderProverParams::deserialize_compressed(&mut reader)?;
+    let decider_vp = DeciderVerifierParams::deserialize_compressed(&mut reader)?;
+    check_params_id(id, &decider_vp, domain)?;
+    Ok(((nova_pp, nova_vp), decider_pp, decider_vp))
+}
+
+pub fn serialize_decider_verifier_params(
+    decider_vp: &DeciderVerifierParams,
+    domain: Fr,
+) -> Result<Vec<u8>, EthDosError> {
+    let mut b = vec![];
+    decider_vp.serialize_compressed(&mut b)?;
+    Ok(format::seal(
+        Kind::DeciderVerifierParams,
+        Scheme::Decider,
+        circuit_digest()?,
+        domain_bytes(domain),
+        decider_params_id(decider_vp, domain)?,
+        false,
+        &b,
+    ))
+}
+
+pub fn deserialize_decider_verifier_params(
+    b: &[u8],
+    domain: Fr,
+) -> Result<DeciderVerifierParams, EthDosError> {
+    let id = format::read_header(b)?.params_id;
+    let b = format::open(
+        Kind::DeciderVerifierParams,
+        Scheme::Decider,
+        circuit_digest()?,
+        domain_bytes(domain),
+        b,
+    )?;
+    let decider_vp = DeciderVerifierParams::deserialize_compressed(b.as_slice())?;
+    check_params_id(id, &decider_vp, domain)?;
+    Ok(decider_vp)
+}
+
+/// Serializes the compressed proof of the chains of the given domain. `params_id` is the id of the
+/// Decider params with which the proof was generated (see `decider_params_id`).
+pub fn serialize_compressed_proof(
+    proof: &CompressedProof,
+    domain: Fr,
+    params_id: [u8; 32],
+) -> Result<Vec<u8>, EthDosError> {
+    let mut b = vec![];
+    proof.serialize_compressed(&mut b)?;
+    Ok(format::seal(
+        Kind::CompressedProof,
+        Scheme::Decider,
+        circuit_digest()?,
+        domain_bytes(domain),
+        params_id,
+        false,
+        &b,
+    ))
+}
+
+/// Inverse of `serialize_compressed_proof`, checking that the proof was generated with the params
+/// of id `params_id`.
+pub fn deserialize_compressed_proof(
+    b: &[u8],
+    domain: Fr,
+    params_id: [u8; 32],
+) -> Result<CompressedProof, EthDosError> {
+    if format::read_header(b)?.params_id != params_id {
+        return Err(EthDosError::Format(
+            "params id mismatch, the proof was generated with different params".to_string(),
+        ));
+    }
+    let b = format::open(
+        Kind::CompressedProof,
+        Scheme::Decider,
+        circuit_digest()?,
+        domain_bytes(domain),
+        b,
+    )?;
+    Ok(CompressedProof::deserialize_compressed(b.as_slice())?)
+}
+
+// checks the id of the header of the Decider params against the one of their content
+fn check_params_id(
+    id: [u8; 32],
+    decider_vp: &DeciderVerifierParams,
+    domain: Fr,
+) -> Result<(), EthDosError> {
+    if decider_params_id(decider_vp, domain)? != id {
+        return Err(EthDosError::Format(
+            "params id mismatch, the params do not match the id of their header".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the Solidity code of the contract that verifies the Decider proofs.
+pub fn solidity_verifier(decider_vp: DeciderVerifierParams) -> Result<String, EthDosError> {
+    // only the state length is used, which does not depend on the domain
+    let f_circuit = FC::new(circuit_params(default_domain()))?;
+
+    let nova_cyclefold_vk = NovaCycleFoldVerifierKey::from((decider_vp, f_circuit.state_len()));
+    Ok(get_decider_template_for_cyclefold_decider(
+        nova_cyclefold_vk,
+    ))
+}
+
+/// Returns the calldata to be sent to the Solidity verifier contract to verify the given proof.
+pub fn calldata(proof: &CompressedProof) -> Result<Vec<u8>, EthDosError> {
+    let function_selector =
+        get_function_selector_for_nova_cyclefold_verifier(proof.z_0.len() * 2 + 1);
+    Ok(prepare_calldata(
+        function_selector,
+        proof.i,
+        proof.z_0.clone(),
+        proof.z_i.clone(),
+        &proof.U_i,
+        &proof.u_i,
+        &proof.proof,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_std::Zero;
+
+    use super::*;
+    use crate::api::check_presentation_labels;
+    use crate::signature::{self, gen_signatures, Attributes, Validity, NO_LABEL};
+
+    #[test]
+    fn test_decider() {
+        const N_STEPS: usize = 2;
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let domain = default_domain();
+
+        let (fs_params, decider_pp, decider_vp) = gen_decider_params(domain).unwrap();
+        let sigs = gen_signatures::<OsRng, EdwardsProjective>(
+            &mut OsRng,
+            &poseidon_config,
+            domain,
+            N_STEPS,
+        )
+        .unwrap();
+
+        let revocations = RevocationTree::new(&poseidon_config);
+        let proof =
+            fold_sigs_compressed(&fs_params, decider_pp, domain, &sigs, &revocations).unwrap();
+        verify_compressed(decider_vp.clone(), &proof).unwrap();
+
+        // the proof and the verifier params survive a serialization roundtrip, and the proof is
+        // tagged with the id of its params
+        let id = decider_params_id(&decider_vp, domain).unwrap();
+        let b = serialize_compressed_proof(&proof, domain, id).unwrap();
+        assert!(deserialize_compressed_proof(&b, domain, [0u8; 32]).is_err());
+        let proof = deserialize_compressed_proof(&b, domain, id).unwrap();
+        let vp = serialize_decider_verifier_params(&decider_vp, domain).unwrap();
+        assert_eq!(params_id(&vp).unwrap(), id);
+        let decider_vp = deserialize_decider_verifier_params(&vp, domain).unwrap();
+
+        let now = get_time() / 1000;
+        let state = verify_compressed_state(
+            decider_vp.clone(),
+            &proof,
+            sigs[0].pk,
+            revocations.root(),
+            now,
+            Some(sigs[N_STEPS - 1].pk),
+            Some(N_STEPS as u64),
+        )
+        .unwrap();
+        assert_eq!(state.degree, N_STEPS as u64);
+        // a valid proof for another pk_0 or revocation tree is rejected
+        assert!(verify_compressed_state(
+            decider_vp.clone(),
+            &proof,
+            sigs[1].pk,
+            revocations.root(),
+            now,
+            None,
+            None,
+        )
+        .is_err());
+        assert!(verify_compressed_state(
+            decider_vp.clone(),
+            &proof,
+            sigs[0].pk,
+            Fr::from(1_u32),
+            now,
+            None,
+            None,
+        )
+        .is_err());
+
+        // a proof claiming a different degree is rejected
+        let mut tampered = proof.clone();
+        tampered.z_i[4] += Fr::from(1_u32);
+        assert!(verify_compressed(decider_vp.clone(), &tampered).is_err());
+
+        // calldata starts with the verifier's function selector
+        let data = calldata(&proof).unwrap();
+        let function_selector =
+            get_function_selector_for_nova_cyclefold_verifier(proof.z_0.len() * 2 + 1);
+        assert_eq!(data[..4], function_selector);
+
+        let code = solidity_verifier(decider_vp).unwrap();
+        assert!(code.contains("NovaDecider"));
+    }
+
+    // test that only the owner of the last public key can present the chain, within the
+    // max_degree, and that the presentation keeps the validity window and the labels, but neither
+    // pk_i, the weight nor the degree
+    #[test]
+    fn test_presentation() {
+        const NOW: u64 = 1_700_000_000;
+        const IN_PERSON: u8 = 1;
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let domain = default_domain();
+        let (sk_0, pk_0) = keys::keygen(&mut OsRng).unwrap();
+        let (sk_1, pk_1) = keys::keygen(&mut OsRng).unwrap();
+        let validity = Validity::new(NOW - 10, NOW + 10).unwrap();
+        let in_person = Attributes::new(IN_PERSON, 2).unwrap();
+        let sigs = vec![
+            keys::sign_pk(&sk_0, domain, pk_0).unwrap(),
+            keys::sign_vouch(&sk_1, domain, pk_0, Some(validity), Some(in_person)).unwrap(),
+        ];
+
+        let (fs_params, decider_pp, decider_vp) = gen_decider_params(domain).unwrap();
+        let revocations = RevocationTree::new(&poseidon_config);
+        let root = revocations.root();
+        let challenge = Fr::from(42_u32);
+
+        assert!(present(
+            &fs_params,
+            decider_pp.clone(),
+            domain,
+            &sigs,
+            &revocations,
+            &sk_0,
+            challenge,
+            3
+        )
+        .is_err());
+        // the chain of degree 2 can not be presented for a max_degree of 1
+        assert!(present(
+            &fs_params,
+            decider_pp.clone(),
+            domain,
+            &sigs,
+            &revocations,
+            &sk_1,
+            challenge,
+            1
+        )
+        .is_err());
+        let proof = present(
+            &fs_params,
+            decider_pp,
+            domain,
+            &sigs,
+            &revocations,
+            &sk_1,
+            challenge,
+            3,
+        )
+        .unwrap();
+        // padded to max_degree + 1 steps
+        assert_eq!(proof.i, Fr::from(4_u32));
+
+        let presentation =
+            verify_presentation(decider_vp.clone(), &proof, pk_0, root, NOW, challenge, 3).unwrap();
+        assert!(presentation.within_max_degree);
+        assert_eq!(
+            presentation.nullifier,
+            signature::nullifier(&poseidon_config, pk_1, challenge).unwrap()
+        );
+        assert_eq!(presentation.validity, validity);
+        check_presentation_labels(&presentation, &[NO_LABEL, IN_PERSON]).unwrap();
+        assert!(check_presentation_labels(&presentation, &[NO_LABEL]).is_err());
+        assert!(!proof.z_i.contains(&pk_1.0.x));
+        assert_eq!(proof.z_i[12], Fr::zero());
+
+        // the proof is bound to the challenge, to the max_degree and to the validity window
+        for (now, challenge, max_degree) in [
+            (NOW, Fr::from(43_u32), 3),
+            (NOW, challenge, 2),
+            (NOW + 10, challenge, 3),
+        ] {
+            assert!(verify_presentation(
+                decider_vp.clone(),
+                &proof,
+                pk_0,
+                root,
+                now,
+                challenge,
+                max_degree
+            )
+            .is_err());
+        }
+        // and it is not a vouching proof
+        assert!(verify_compressed_state(decider_vp, &proof, pk_0, root, NOW, None, None).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! This file contains the error type returned by the native API and converted into a JavaScript
+//! error by the WASM bindings.
+use ark_serialize::SerializationError;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum EthDosError {
+    /// Invalid base64 input
+    Decoding(base64::DecodeError),
+    /// Bytes that do not represent the expected object (truncated bytes, invalid curve points, etc)
+    Deserialization(String),
+    /// Missing or mismatching header of a serialized artefact
+    Format(String),
+    /// Failed lz4 decompression of a proof
+    Decompression(lz4_flex::block::DecompressError),
+    /// Error when signing, or invalid signature
+    Signature(String),
+    /// The chain of signatures to be folded is broken at the given index
+    Chain { index: usize, reason: String },
+    /// No chain of signatures between the given public keys
+    Graph(String),
+    /// Revoked public key or vouch, or conflicting revocation
+    Revocation(String),
+    /// Error when deriving, encrypting or decrypting the secret keys
+    Key(String),
+    /// Error from the folding scheme when generating params or proving
+    Folding(folding_schemes::Error),
+    /// The proof does not verify, or the statement that it proves is not the expected one
+    Verification(String),
+    /// Error when reading or writing the params cache
+    Io(std::io::Error),
+    /// Error when building the thread pool of the batch API
+    ThreadPool(String),
+    /// The folding was cancelled through its cancellation token
+    Cancelled,
+}
+
+impl fmt::Display for EthDosError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EthDosError::Decoding(e) => write!(f, "base64 decoding error: {}", e),
+            EthDosError::Deserialization(e) => write!(f, "deserialization error: {}", e),
+            EthDosError::Format(e) => write!(f, "format error: {}", e),
+            EthDosError::Decompression(e) => write!(f, "decompression error: {}", e),
+            EthDosError::Signature(e) => write!(f, "signature error: {}", e),
+            EthDosError::Chain { index, reason } => {
+                write!(f, "invalid signature chain at index {}: {}", index, reason)
+            }
+            EthDosError::Graph(e) => write!(f, "signature graph error: {}", e),
+            EthDosError::Revocation(e) => write!(f, "revocation error: {}", e),
+            EthDosError::Key(e) => write!(f, "key error: {}", e),
+            EthDosError::Folding(e) => write!(f, "folding error: {}", e),
+            EthDosError::Verification(e) => write!(f, "verification error: {}", e),
+            EthDosError::Io(e) => write!(f, "io error: {}", e),
+            EthDosError::ThreadPool(e) => write!(f, "thread pool error: {}", e),
+            EthDosError::Cancelled => write!(f, "the folding was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for EthDosError {}
+
+impl From<base64::DecodeError> for EthDosError {
+    fn from(e: base64::DecodeError) -> Self {
+        EthDosError::Decoding(e)
+    }
+}
+impl From<SerializationError> for EthDosError {
+    fn from(e: SerializationError) -> Self {
+        EthDosError::Deserialization(e.to_string())
+    }
+}
+impl From<lz4_flex::block::DecompressError> for EthDosError {
+    fn from(e: lz4_flex::block::DecompressError) -> Self {
+        EthDosError::Decompression(e)
+    }
+}
+impl From<folding_schemes::Error> for EthDosError {
+    fn from(e: folding_schemes::Error) -> Self {
+        EthDosError::Folding(e)
+    }
+}
+impl From<std::io::Error> for EthDosError {
+    fn from(e: std::io::Error) -> Self {
+        EthDosError::Io(e)
+    }
+}
+
+impl From<EthDosError> for wasm_bindgen::JsValue {
+    fn from(e: EthDosError) -> Self {
+        wasm_bindgen::JsError::new(&e.to_string()).into()
+    }
+}
@@ -5,49 +5,172 @@ use ark_crypto_primitives::sponge::{
     Absorb,
 };
 use ark_ec::CurveGroup;
-use ark_ff::{Field, PrimeField};
+use ark_ff::{BigInteger, Field, PrimeField};
 use ark_r1cs_std::prelude::CurveVar;
 use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
     boolean::Boolean,
     eq::EqGadget,
     fields::{fp::FpVar, FieldVar},
+    R1CSVar,
 };
-use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
-use ark_std::marker::PhantomData;
+use ark_relations::r1cs::{ConstraintSystemRef, Namespace, SynthesisError};
+use ark_std::{marker::PhantomData, Zero};
+use core::borrow::Borrow;
+use core::cmp::Ordering;
 use std::fmt::Debug;
 
 use arkeddsa::constraints::verify;
 use folding_schemes::{frontend::FCircuit, Error};
 
-use crate::signature::{SigPk, SigPkVar};
+use crate::revocation::{enforce_non_membership, vouch_key_var, NonRevocation, NonRevocationVar};
+use crate::signature::{
+    enforce_not_small_order, SigPk, SigPkVar, DEFAULT_WEIGHT, NO_EXPIRY, NO_LABEL, N_LABELS,
+    PRESENTATION_TAG,
+};
 
 pub type CF<C> = <<C as CurveGroup>::BaseField as Field>::BasePrimeField;
 
-/// Test circuit to be folded
+/// External inputs of each folding step. In a 'vouch' step (the usual one), `sig_pk` is the
+/// signature of the new public key over the previous one. In a 'presentation' step, which is the
+/// last one of the chain, `sig_pk` is the signature of the last public key over the verifier's
+/// `challenge`, the last public key is replaced in the state by a nullifier, and the degree by the
+/// verifier's `max_degree` together with the result of `degree <= max_degree`. The presentation
+/// step is followed by 'padding' steps, which keep the state, up to `max_degree + 1` steps.
+///
+/// The vouch steps also contain the proofs that the new public key and its vouch are not revoked
+/// (see `revocation.rs`), which are ignored in the presentation step. When the vouch has a validity
+/// window (`sig_pk.validity`), the window of the chain kept in the state is narrowed to it. The
+/// weight and label of the vouch (`sig_pk.attributes`) are accumulated in the state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EthDosStep<C: CurveGroup> {
+    pub sig_pk: SigPk<C>,
+    pub non_revocation: NonRevocation<CF<C>>,
+    pub presentation: bool,
+    pub challenge: CF<C>,
+    pub max_degree: CF<C>,
+}
+impl<C: CurveGroup> Default for EthDosStep<C> {
+    fn default() -> Self {
+        Self::vouch(SigPk::default(), NonRevocation::default())
+    }
+}
+impl<C: CurveGroup> EthDosStep<C> {
+    pub fn vouch(sig_pk: SigPk<C>, non_revocation: NonRevocation<CF<C>>) -> Self {
+        Self {
+            sig_pk,
+            non_revocation,
+            presentation: false,
+            challenge: CF::<C>::zero(),
+            max_degree: CF::<C>::zero(),
+        }
+    }
+    pub fn present(sig_pk: SigPk<C>, challenge: CF<C>, max_degree: u64) -> Self {
+        Self {
+            sig_pk,
+            non_revocation: NonRevocation::default(),
+            presentation: true,
+            challenge,
+            max_degree: CF::<C>::from(max_degree),
+        }
+    }
+    pub fn pad() -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EthDosStepVar<C: CurveGroup, GC: CurveVar<C, CF<C>>> {
+    pub sig_pk: SigPkVar<C, GC>,
+    pub non_revocation: NonRevocationVar<CF<C>>,
+    pub presentation: Boolean<CF<C>>,
+    pub challenge: FpVar<CF<C>>,
+    pub max_degree: FpVar<CF<C>>,
+}
+impl<C: CurveGroup, GC: CurveVar<C, CF<C>>> Default for EthDosStepVar<C, GC> {
+    fn default() -> Self {
+        Self {
+            sig_pk: SigPkVar::default(),
+            non_revocation: NonRevocationVar::default(),
+            presentation: Boolean::<CF<C>>::FALSE,
+            challenge: FpVar::<CF<C>>::zero(),
+            max_degree: FpVar::<CF<C>>::zero(),
+        }
+    }
+}
+
+impl<C, GC> AllocVar<EthDosStep<C>, CF<C>> for EthDosStepVar<C, GC>
+where
+    C: CurveGroup,
+    GC: CurveVar<C, CF<C>>,
+{
+    fn new_variable<T: Borrow<EthDosStep<C>>>(
+        cs: impl Into<Namespace<CF<C>>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        f().and_then(|val| {
+            let cs = cs.into();
+
+            let e = val.borrow();
+            let sig_pk = SigPkVar::new_variable(cs.clone(), || Ok(e.sig_pk), mode)?;
+            let non_revocation =
+                NonRevocationVar::new_variable(cs.clone(), || Ok(e.non_revocation.clone()), mode)?;
+            let presentation = Boolean::new_variable(cs.clone(), || Ok(e.presentation), mode)?;
+            let challenge = FpVar::new_variable(cs.clone(), || Ok(e.challenge), mode)?;
+            let max_degree = FpVar::new_variable(cs.clone(), || Ok(e.max_degree), mode)?;
+            Ok(Self {
+                sig_pk,
+                non_revocation,
+                presentation,
+                challenge,
+                max_degree,
+            })
+        })
+    }
+}
+
+/// Params of the `EthDosCircuit`: the Poseidon config, and the domain tag (see
+/// `signature::domain_tag`) of the vouches, which is a constant of the circuit, so that the graphs
+/// of different domains are isolated from each other.
+#[derive(Clone, Debug)]
+pub struct EthDosParams<F: PrimeField> {
+    pub poseidon_config: PoseidonConfig<F>,
+    pub domain: F,
+}
+
+/// Step circuit of the IVC. Each step either verifies a vouch from `pk_i` (checking its
+/// signature, validity window and non-revocation) and increments the degree, or, once all the
+/// vouches are folded, performs the presentation step, which replaces `pk_i` by its nullifier for
+/// the verifier's challenge and the degree by the comparison with the verifier's `max_degree`.
+/// After the presentation step, the steps are padding ones, which keep the state, so that the
+/// number of steps of a presentation is `max_degree + 1` whatever the degree.
 #[derive(Clone, Debug)]
 pub struct EthDosCircuit<F: PrimeField, C: CurveGroup, GC: CurveVar<C, F>> {
     _c: PhantomData<C>,
     _gc: PhantomData<GC>,
     config: PoseidonConfig<F>,
+    domain: F,
 }
 impl<F: PrimeField, C: CurveGroup, GC: CurveVar<C, F>> FCircuit<F> for EthDosCircuit<F, C, GC>
 where
     F: Absorb,
     C: CurveGroup<BaseField = F>,
 {
-    type Params = PoseidonConfig<F>;
-    type ExternalInputs = SigPk<C>;
-    type ExternalInputsVar = SigPkVar<C, GC>;
+    type Params = EthDosParams<F>;
+    type ExternalInputs = EthDosStep<C>;
+    type ExternalInputsVar = EthDosStepVar<C, GC>;
 
-    fn new(config: Self::Params) -> Result<Self, Error> {
+    fn new(params: Self::Params) -> Result<Self, Error> {
         Ok(Self {
             _c: PhantomData,
             _gc: PhantomData,
-            config,
+            config: params.poseidon_config,
+            domain: params.domain,
         })
     }
     fn state_len(&self) -> usize {
-        5
+        14
     }
     // This method defines the logic that is done in-circuit at each folding step
     fn generate_step_constraints(
@@ -57,55 +180,278 @@ where
         z_i: Vec<FpVar<F>>,
         external_inputs: Self::ExternalInputsVar,
     ) -> Result<Vec<FpVar<F>>, SynthesisError> {
-        // get the values from the state, where: state = [ pk_0, pk_i, i]
+        // get the values from the state, where:
+        // state = [ pk_0, pk_i, i, presented, nullifier, challenge, within_max_degree,
+        //           revocation_root, not_before, not_after, weight, labels]
+        // and after the presentation step the degree i is replaced by the max_degree.
+        // [not_before, not_after) is the validity window of the chain, ie. the intersection of the
+        // validity windows of its vouches, weight is the sum of the weights of its vouches, and
+        // labels is the bitmask of the labels of its vouches
         let pk_0_x = z_i[0].clone();
         let pk_0_y = z_i[1].clone();
         let pk_i_x = z_i[2].clone();
         let pk_i_y = z_i[3].clone();
-        let mut degree = z_i[4].clone();
+        let degree = z_i[4].clone();
+        let presented = z_i[5].clone();
+        let revocation_root = z_i[9].clone();
+        let not_before = z_i[10].clone();
+        let not_after = z_i[11].clone();
+        let weight = z_i[12].clone();
+        let labels = z_i[13].clone();
+
+        // after the presentation step (presented = 1), the only steps are the padding ones, which
+        // check nothing and keep the state
+        let zero = FpVar::<F>::zero();
+        let one = FpVar::<F>::one();
+        (presented.clone() * (presented.clone() - one.clone())).enforce_equal(&zero)?;
+        let is_padding = presented.is_one()?;
+        let is_step = !is_padding.clone();
+        let is_presentation = external_inputs.presentation.clone();
+        is_presentation.conditional_enforce_equal(&Boolean::FALSE, &is_padding)?;
+        let challenge = external_inputs.challenge.clone();
 
-        // get the 'pk_i_hashed' value, which is the hash of the pk_i, and is the value that has
-        // been signed by the new public key (pk_i+1)
+        // get the 'pk_i_hashed' value, which is the hash of the domain and pk_i, and is the value
+        // that has been signed by the new public key (pk_i+1)
+        let domain = FpVar::<F>::constant(self.domain);
         let mut poseidon = PoseidonSpongeVar::new(cs.clone(), &self.config);
-        poseidon.absorb(&vec![pk_i_x, pk_i_y])?;
+        poseidon.absorb(&vec![domain.clone(), pk_i_x.clone(), pk_i_y.clone()])?;
         let h = poseidon.squeeze_field_elements(1)?;
         let pk_i_hashed = h
             .first()
             .ok_or(ark_relations::r1cs::SynthesisError::Unsatisfiable)?;
 
-        // check that the last signer's public key (pk_i) hashed (=pk_i_hashed) is signed by the
-        // new signer public key (pk_i+1)
+        // the vouches with a validity window sign it together with pk_i
+        let sig_pk = external_inputs.sig_pk.clone();
+        let mut poseidon = PoseidonSpongeVar::new(cs.clone(), &self.config);
+        poseidon.absorb(&vec![
+            domain,
+            pk_i_x.clone(),
+            pk_i_y.clone(),
+            sig_pk.not_before.clone(),
+            sig_pk.not_after.clone(),
+        ])?;
+        let h = poseidon.squeeze_field_elements(1)?;
+        let pk_i_validity_hashed = h
+            .first()
+            .ok_or(ark_relations::r1cs::SynthesisError::Unsatisfiable)?;
+        let vouch_msg = sig_pk
+            .has_validity
+            .select(pk_i_validity_hashed, pk_i_hashed)?;
+
+        // and the vouches with attributes sign them together with the previous message
+        let mut poseidon = PoseidonSpongeVar::new(cs.clone(), &self.config);
+        poseidon.absorb(&vec![
+            vouch_msg.clone(),
+            sig_pk.label.clone(),
+            sig_pk.weight.clone(),
+        ])?;
+        let h = poseidon.squeeze_field_elements(1)?;
+        let attributes_hashed = h
+            .first()
+            .ok_or(ark_relations::r1cs::SynthesisError::Unsatisfiable)?;
+        let vouch_msg = sig_pk
+            .has_attributes
+            .select(attributes_hashed, &vouch_msg)?;
+
+        // in the presentation step, the signed message is the hash of the challenge instead
+        let mut poseidon = PoseidonSpongeVar::new(cs.clone(), &self.config);
+        poseidon.absorb(&vec![
+            FpVar::<F>::constant(F::from(PRESENTATION_TAG)),
+            challenge.clone(),
+        ])?;
+        let h = poseidon.squeeze_field_elements(1)?;
+        let challenge_hashed = h
+            .first()
+            .ok_or(ark_relations::r1cs::SynthesisError::Unsatisfiable)?;
+        let msg = is_presentation.select(challenge_hashed, &vouch_msg)?;
+
+        // check that the message is signed by the signer of the step, which is the new signer
+        // public key (pk_i+1) in the vouch steps. Neither the public key nor the R of the
+        // signature can be of small order, otherwise the signature could be forged without the
+        // secret key
+        enforce_not_small_order::<C, GC>(&sig_pk.pk, &is_step)?;
+        enforce_not_small_order::<C, GC>(&sig_pk.sig_r, &is_step)?;
         let res = verify::<C, GC>(
             cs.clone(),
             self.config.clone(),
-            external_inputs.pk.clone(), // pk_{i+1}
-            (external_inputs.sig_r, external_inputs.sig_s),
-            pk_i_hashed.clone(),
+            sig_pk.pk.clone(),
+            (sig_pk.sig_r, sig_pk.sig_s),
+            msg,
+        )?;
+        res.conditional_enforce_equal(&Boolean::<F>::TRUE, &is_step)?;
+
+        let pk_i1_xy = external_inputs.sig_pk.pk.to_constraint_field()?;
+
+        // in the presentation step, the signer has to be the last public key (pk_i), ie. it is a
+        // proof of knowledge of its secret key
+        pk_i1_xy[0].conditional_enforce_equal(&pk_i_x, &is_presentation)?;
+        pk_i1_xy[1].conditional_enforce_equal(&pk_i_y, &is_presentation)?;
+
+        // in the vouch steps, check that neither the new public key nor its vouch of pk_i are in
+        // the revocation tree
+        let is_vouch = Boolean::kary_and(&[!is_presentation.clone(), is_step])?;
+        let mut poseidon = PoseidonSpongeVar::new(cs.clone(), &self.config);
+        poseidon.absorb(&vec![pk_i1_xy[0].clone(), pk_i1_xy[1].clone()])?;
+        let h = poseidon.squeeze_field_elements(1)?;
+        let pk_i1_hashed = h
+            .first()
+            .ok_or(ark_relations::r1cs::SynthesisError::Unsatisfiable)?;
+        enforce_non_membership(
+            cs.clone(),
+            &self.config,
+            &revocation_root,
+            pk_i1_hashed,
+            &external_inputs.non_revocation.pk,
+            &is_vouch,
+        )?;
+        let vouch_key = vouch_key_var(
+            cs.clone(),
+            &self.config,
+            (pk_i_x.clone(), pk_i_y.clone()),
+            (pk_i1_xy[0].clone(), pk_i1_xy[1].clone()),
+        )?;
+        enforce_non_membership(
+            cs.clone(),
+            &self.config,
+            &revocation_root,
+            &vouch_key,
+            &external_inputs.non_revocation.vouch,
+            &is_vouch,
         )?;
-        res.enforce_equal(&Boolean::<F>::TRUE)?;
 
-        // increment the degree
-        degree = degree.clone() + FpVar::<F>::one();
+        // narrow the validity window of the chain to the one of the vouch, where the vouches
+        // without validity window are valid from 0 and do not expire
+        let no_validity = !sig_pk.has_validity.clone();
+        sig_pk
+            .not_before
+            .conditional_enforce_equal(&zero, &no_validity)?;
+        sig_pk
+            .not_after
+            .conditional_enforce_equal(&FpVar::constant(F::from(NO_EXPIRY)), &no_validity)?;
+        let later = sig_pk
+            .not_before
+            .is_cmp(&not_before, Ordering::Greater, false)?;
+        let vouch_not_before = later.select(&sig_pk.not_before, &not_before)?;
+        let earlier = sig_pk.not_after.is_cmp(&not_after, Ordering::Less, false)?;
+        let vouch_not_after = earlier.select(&sig_pk.not_after, &not_after)?;
 
-        // return the new IVC state, where we place the pk_{i+1} at the place where previously had
-        // the pk_i, together with the new updated degree of distance value
-        let pk_i1_xy = external_inputs.pk.to_constraint_field()?;
-        Ok([vec![pk_0_x, pk_0_y], pk_i1_xy, vec![degree]].concat())
+        // accumulate the weight and the label of the vouch, where the vouches without attributes
+        // have weight DEFAULT_WEIGHT and label NO_LABEL. The weight is range checked so that the
+        // sum can not wrap around the field
+        let no_attributes = !sig_pk.has_attributes.clone();
+        sig_pk
+            .label
+            .conditional_enforce_equal(&FpVar::constant(F::from(NO_LABEL)), &no_attributes)?;
+        sig_pk
+            .weight
+            .conditional_enforce_equal(&FpVar::constant(F::from(DEFAULT_WEIGHT)), &no_attributes)?;
+        to_bits_le(cs.clone(), &sig_pk.weight, 32)?;
+        let vouch_weight = weight.clone() + sig_pk.weight.clone();
+        let vouch_labels = set_label(cs.clone(), &labels, &sig_pk.label)?;
+
+        // the nullifier binds pk_i to the challenge without revealing it
+        let mut poseidon = PoseidonSpongeVar::new(cs.clone(), &self.config);
+        poseidon.absorb(&vec![pk_i_x, pk_i_y, challenge.clone()])?;
+        let h = poseidon.squeeze_field_elements(1)?;
+        let nullifier = h
+            .first()
+            .ok_or(ark_relations::r1cs::SynthesisError::Unsatisfiable)?;
+
+        // compare the degree with the verifier's max_degree, so that only the result of the
+        // comparison is revealed
+        let max_degree = external_inputs.max_degree.clone();
+        let within_max_degree = FpVar::from(degree.is_cmp(&max_degree, Ordering::Less, true)?);
+
+        // return the new IVC state. In the vouch steps, we place the pk_{i+1} at the place where
+        // previously had the pk_i, together with the new updated degree of distance value. In the
+        // presentation step, pk_i is removed and the degree replaced by the max_degree.
+        let pk_x = is_presentation.select(&zero, &pk_i1_xy[0])?;
+        let pk_y = is_presentation.select(&zero, &pk_i1_xy[1])?;
+        let degree = is_presentation.select(&max_degree, &(degree.clone() + one.clone()))?;
+        let presented = is_presentation.select(&one, &zero)?;
+        let nullifier = is_presentation.select(nullifier, &zero)?;
+        let challenge = is_presentation.select(&challenge, &zero)?;
+        let within_max_degree = is_presentation.select(&within_max_degree, &zero)?;
+        let not_before = is_presentation.select(&not_before, &vouch_not_before)?;
+        let not_after = is_presentation.select(&not_after, &vouch_not_after)?;
+        // the weight would reveal the degree of the chains of unweighted vouches, so it is removed
+        // in the presentation step
+        let weight = is_presentation.select(&zero, &vouch_weight)?;
+        let labels = is_presentation.select(&labels, &vouch_labels)?;
+        let z_i1 = vec![
+            pk_0_x,
+            pk_0_y,
+            pk_x,
+            pk_y,
+            degree,
+            presented,
+            nullifier,
+            challenge,
+            within_max_degree,
+            revocation_root,
+            not_before,
+            not_after,
+            weight,
+            labels,
+        ];
+        // the padding steps keep the state
+        z_i.iter()
+            .zip(z_i1.iter())
+            .map(|(z, z1)| is_padding.select(z, z1))
+            .collect()
     }
 }
 
+// returns the little-endian bits of `v`, enforcing that it fits in `n_bits` bits
+fn to_bits_le<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    v: &FpVar<F>,
+    n_bits: usize,
+) -> Result<Vec<Boolean<F>>, SynthesisError> {
+    let value = v.value().unwrap_or_default().into_bigint();
+    let bits = (0..n_bits)
+        .map(|i| Boolean::new_witness(cs.clone(), || Ok(value.get_bit(i))))
+        .collect::<Result<Vec<_>, _>>()?;
+    Boolean::le_bits_to_fp(&bits)?.enforce_equal(v)?;
+    Ok(bits)
+}
+
+// returns the bitmask `labels` with the bit `label` set, enforcing that `label < N_LABELS`
+fn set_label<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    labels: &FpVar<F>,
+    label: &FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    let bits = to_bits_le(cs, labels, N_LABELS as usize)?;
+    let mut n_matches = FpVar::<F>::zero();
+    let mut new_bits = Vec::with_capacity(bits.len());
+    for (i, bit) in bits.into_iter().enumerate() {
+        let is_label = label.is_eq(&FpVar::constant(F::from(i as u64)))?;
+        n_matches += FpVar::from(is_label.clone());
+        new_bits.push(Boolean::kary_or(&[bit, is_label])?);
+    }
+    n_matches.enforce_equal(&FpVar::one())?;
+    Boolean::le_bits_to_fp(&new_bits)
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
     use ark_bn254::Fr;
-    use ark_ec::AffineRepr;
-    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_ec::{AffineRepr, PrimeGroup};
+    use ark_ff::One;
+    use ark_r1cs_std::R1CSVar;
     use ark_relations::r1cs::ConstraintSystem;
     use ark_std::Zero;
     use rand::rngs::OsRng;
 
-    use crate::signature::{gen_signatures, hash_pk};
+    use crate::revocation::RevocationTree;
+    use crate::signature::{
+        domain_tag, gen_signatures, hash_pk, nullifier, sign_challenge, sign_pk,
+        sign_pk_with_validity, sign_vouch, Attributes, Validity, DEFAULT_CONTEXT,
+    };
     use arkeddsa::ed_on_bn254_twist::{constraints::EdwardsVar, EdwardsProjective};
+    use arkeddsa::SigningKey;
     use folding_schemes::transcript::poseidon::poseidon_canonical_config;
 
     #[test]
@@ -113,11 +459,15 @@ pub mod tests {
         let mut rng = OsRng;
         let poseidon_config = poseidon_canonical_config::<Fr>();
 
+        let domain = domain_tag(DEFAULT_CONTEXT);
+
         const N: usize = 1;
-        let ext_inps = gen_signatures::<OsRng, EdwardsProjective>(&mut rng, &poseidon_config, 1);
+        let ext_inps =
+            gen_signatures::<OsRng, EdwardsProjective>(&mut rng, &poseidon_config, domain, 1)
+                .unwrap();
         let e = ext_inps[0];
 
-        let msg = hash_pk(&poseidon_config, e.pk);
+        let msg = hash_pk(&poseidon_config, domain, e.pk).unwrap();
 
         e.pk.verify(&poseidon_config, &msg, &e.sig).unwrap();
     }
@@ -126,28 +476,55 @@ pub mod tests {
         let _ = FC::new(params);
     }
 
-    // test to check that the Sha256FCircuit computes the same values inside and outside the circuit
+    fn circuit_params(poseidon_config: &PoseidonConfig<Fr>, context: &str) -> EthDosParams<Fr> {
+        EthDosParams {
+            poseidon_config: poseidon_config.clone(),
+            domain: domain_tag(context),
+        }
+    }
+
+    // test to check that the vouch step of EthDosCircuit computes the expected state and is not
+    // satisfied once the vouch is revoked
     #[test]
     fn test_fcircuit() {
         let mut rng = rand::rngs::OsRng;
         let poseidon_config = poseidon_canonical_config::<Fr>();
 
-        let pks_sigs = gen_signatures::<OsRng, EdwardsProjective>(&mut rng, &poseidon_config, 1);
+        let pks_sigs = gen_signatures::<OsRng, EdwardsProjective>(
+            &mut rng,
+            &poseidon_config,
+            domain_tag(DEFAULT_CONTEXT),
+            1,
+        )
+        .unwrap();
 
         // here `Fr` is the BN254::G1::Fr = ed_on_bn254_twist::EdwardsProjective::Fq
         let cs = ConstraintSystem::<Fr>::new_ref();
 
         type FC = EthDosCircuit<Fr, EdwardsProjective, EdwardsVar>;
-        ensure_fcircuit_trait::<FC>(poseidon_config.clone());
+        ensure_fcircuit_trait::<FC>(circuit_params(&poseidon_config, DEFAULT_CONTEXT));
 
-        let circuit = FC::new(poseidon_config).unwrap();
+        let circuit = FC::new(circuit_params(&poseidon_config, DEFAULT_CONTEXT)).unwrap();
+        let revocations = RevocationTree::new(&poseidon_config);
         let xy: (Fr, Fr) = pks_sigs[0].pk.0.xy().unwrap();
         let pk0 = vec![xy.0, xy.1];
-        let z_i: Vec<Fr> = [pk0.clone(), pk0, vec![Fr::zero()]].concat();
+        let z_i: Vec<Fr> = [
+            pk0.clone(),
+            pk0,
+            vec![Fr::zero(); 5],
+            vec![revocations.root(), Fr::zero(), Fr::from(NO_EXPIRY)],
+            vec![Fr::zero(); 2],
+        ]
+        .concat();
 
+        let non_revocation = revocations
+            .non_revocation(pks_sigs[0].pk, pks_sigs[0].pk)
+            .unwrap();
         let external_inputs_var =
-            SigPkVar::<EdwardsProjective, EdwardsVar>::new_witness(cs.clone(), || Ok(pks_sigs[0]))
-                .unwrap();
+            EthDosStepVar::<EdwardsProjective, EdwardsVar>::new_witness(cs.clone(), || {
+                Ok(EthDosStep::vouch(pks_sigs[0], non_revocation.clone()))
+            })
+            .unwrap();
 
         let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
         let computed_z_i1Var = circuit
@@ -158,5 +535,451 @@ pub mod tests {
         assert!(cs.is_satisfied().unwrap());
         dbg!(cs.num_constraints());
         dbg!(&computed_z_i1Var.value().unwrap());
+
+        // once the public key is revoked, the step is not satisfied for the new revocation root
+        let mut revocations = revocations;
+        revocations.revoke_pk(pks_sigs[0].pk).unwrap();
+        let mut z_i = z_iVar.value().unwrap();
+        z_i[9] = revocations.root();
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let external_inputs_var =
+            EthDosStepVar::<EdwardsProjective, EdwardsVar>::new_witness(cs.clone(), || {
+                Ok(EthDosStep::vouch(pks_sigs[0], non_revocation.clone()))
+            })
+            .unwrap();
+        let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i.clone())).unwrap();
+        circuit
+            .generate_step_constraints(cs.clone(), 0, z_iVar, external_inputs_var)
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // the signatures of a domain are not valid in the circuit of another domain
+        let other_circuit = FC::new(circuit_params(&poseidon_config, "other")).unwrap();
+        let mut z_i = z_i;
+        z_i[9] = RevocationTree::empty_root(&poseidon_config);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let external_inputs_var =
+            EthDosStepVar::<EdwardsProjective, EdwardsVar>::new_witness(cs.clone(), || {
+                Ok(EthDosStep::vouch(pks_sigs[0], non_revocation))
+            })
+            .unwrap();
+        let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+        other_circuit
+            .generate_step_constraints(cs.clone(), 0, z_iVar, external_inputs_var)
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    // test that the presentation step only accepts the signature of pk_i over the challenge, and
+    // that it replaces pk_i by the nullifier and the degree by the comparison with max_degree
+    #[test]
+    fn test_fcircuit_presentation() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        type FC = EthDosCircuit<Fr, EdwardsProjective, EdwardsVar>;
+        let circuit = FC::new(circuit_params(&poseidon_config, DEFAULT_CONTEXT)).unwrap();
+
+        let sk =
+            SigningKey::<EdwardsProjective>::generate::<blake2::Blake2b512>(&mut OsRng).unwrap();
+        let other_sk =
+            SigningKey::<EdwardsProjective>::generate::<blake2::Blake2b512>(&mut OsRng).unwrap();
+        let pk_i = *sk.public_key();
+        let challenge = Fr::from(42_u32);
+        let revocation_root = RevocationTree::empty_root(&poseidon_config);
+
+        // state after some vouch steps, where pk_i is the last public key
+        let pk_0 = *other_sk.public_key();
+        let z_i: Vec<Fr> = vec![
+            pk_0.0.x,
+            pk_0.0.y,
+            pk_i.0.x,
+            pk_i.0.y,
+            Fr::from(3_u32),
+            Fr::zero(),
+            Fr::zero(),
+            Fr::zero(),
+            Fr::zero(),
+            revocation_root,
+            Fr::from(10_u32),
+            Fr::from(20_u32),
+            Fr::from(3_u32),
+            Fr::from(0b101_u32),
+        ];
+
+        for (signer, max_degree, satisfied) in
+            [(&sk, 3, true), (&sk, 2, true), (&other_sk, 3, false)]
+        {
+            let sig_pk = sign_challenge(signer, &poseidon_config, challenge).unwrap();
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let step =
+                EthDosStepVar::<EdwardsProjective, EdwardsVar>::new_witness(cs.clone(), || {
+                    Ok(EthDosStep::present(sig_pk, challenge, max_degree))
+                })
+                .unwrap();
+            let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i.clone())).unwrap();
+            let z_i1 = circuit
+                .generate_step_constraints(cs.clone(), 3, z_iVar, step)
+                .unwrap()
+                .value()
+                .unwrap();
+            assert_eq!(cs.is_satisfied().unwrap(), satisfied);
+            if satisfied {
+                assert_eq!(
+                    z_i1,
+                    vec![
+                        pk_0.0.x,
+                        pk_0.0.y,
+                        Fr::zero(),
+                        Fr::zero(),
+                        Fr::from(max_degree),
+                        Fr::from(1_u32),
+                        nullifier(&poseidon_config, pk_i, challenge).unwrap(),
+                        challenge,
+                        Fr::from(3 <= max_degree),
+                        revocation_root,
+                        Fr::from(10_u32),
+                        Fr::from(20_u32),
+                        Fr::zero(),
+                        Fr::from(0b101_u32),
+                    ]
+                );
+            }
+        }
+    }
+
+    // test that the steps after the presentation step keep the state, and that they can not be
+    // presentation steps nor be done before the presentation step
+    #[test]
+    fn test_fcircuit_padding() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        type FC = EthDosCircuit<Fr, EdwardsProjective, EdwardsVar>;
+        let circuit = FC::new(circuit_params(&poseidon_config, DEFAULT_CONTEXT)).unwrap();
+
+        let sk =
+            SigningKey::<EdwardsProjective>::generate::<blake2::Blake2b512>(&mut OsRng).unwrap();
+        let pk_0 = *sk.public_key();
+        let challenge = Fr::from(42_u32);
+
+        // state after the presentation step of a chain of degree 2 with max_degree 3
+        let z_i: Vec<Fr> = vec![
+            pk_0.0.x,
+            pk_0.0.y,
+            Fr::zero(),
+            Fr::zero(),
+            Fr::from(3_u32),
+            Fr::from(1_u32),
+            nullifier(&poseidon_config, pk_0, challenge).unwrap(),
+            challenge,
+            Fr::from(1_u32),
+            RevocationTree::empty_root(&poseidon_config),
+            Fr::zero(),
+            Fr::from(NO_EXPIRY),
+            Fr::zero(),
+            Fr::from(1_u32),
+        ];
+        assert!(step_satisfied(
+            &circuit,
+            &z_i,
+            EthDosStep::pad(),
+            Some(&z_i)
+        ));
+
+        // the padding steps can not change the state
+        let mut other_degree = z_i.clone();
+        other_degree[4] = Fr::from(2_u32);
+        assert!(!step_satisfied(
+            &circuit,
+            &z_i,
+            EthDosStep::pad(),
+            Some(&other_degree)
+        ));
+
+        // nor present the chain again
+        let sig_pk = sign_challenge(&sk, &poseidon_config, challenge).unwrap();
+        assert!(!step_satisfied(
+            &circuit,
+            &z_i,
+            EthDosStep::present(sig_pk, challenge, 3),
+            None
+        ));
+
+        // the padding steps are not valid before the presentation step, and the presented flag is
+        // a boolean
+        let mut not_presented = z_i.clone();
+        not_presented[5] = Fr::zero();
+        assert!(!step_satisfied(
+            &circuit,
+            &not_presented,
+            EthDosStep::pad(),
+            None
+        ));
+        let mut not_boolean = z_i;
+        not_boolean[5] = Fr::from(2_u32);
+        assert!(!step_satisfied(
+            &circuit,
+            &not_boolean,
+            EthDosStep::pad(),
+            None
+        ));
+    }
+
+    // test that the vouches with a validity window narrow the window of the chain, and that the
+    // window is bound to the signature
+    #[test]
+    fn test_fcircuit_validity() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        type FC = EthDosCircuit<Fr, EdwardsProjective, EdwardsVar>;
+        let circuit = FC::new(circuit_params(&poseidon_config, DEFAULT_CONTEXT)).unwrap();
+        let revocations = RevocationTree::new(&poseidon_config);
+
+        let sk_0 =
+            SigningKey::<EdwardsProjective>::generate::<blake2::Blake2b512>(&mut OsRng).unwrap();
+        let sk_1 =
+            SigningKey::<EdwardsProjective>::generate::<blake2::Blake2b512>(&mut OsRng).unwrap();
+        let (pk_0, pk_1) = (*sk_0.public_key(), *sk_1.public_key());
+        let validity = Validity::new(100, 200).unwrap();
+        let sig_pk = sign_pk_with_validity(
+            &sk_1,
+            &poseidon_config,
+            circuit.domain,
+            pk_0,
+            Some(validity),
+        )
+        .unwrap();
+        let non_revocation = revocations.non_revocation(pk_0, pk_1).unwrap();
+
+        // chain valid in [50, 150)
+        let z_i: Vec<Fr> = vec![
+            pk_0.0.x,
+            pk_0.0.y,
+            pk_0.0.x,
+            pk_0.0.y,
+            Fr::from(1_u32),
+            Fr::zero(),
+            Fr::zero(),
+            Fr::zero(),
+            Fr::zero(),
+            revocations.root(),
+            Fr::from(50_u32),
+            Fr::from(150_u32),
+            Fr::from(1_u32),
+            Fr::from(1_u32),
+        ];
+
+        let mut tampered = sig_pk;
+        tampered.validity = Some(Validity::new(100, 300).unwrap());
+        let mut without_window = sig_pk;
+        without_window.validity = None;
+        for (sig_pk, satisfied) in [(sig_pk, true), (tampered, false), (without_window, false)] {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let step =
+                EthDosStepVar::<EdwardsProjective, EdwardsVar>::new_witness(cs.clone(), || {
+                    Ok(EthDosStep::vouch(sig_pk, non_revocation.clone()))
+                })
+                .unwrap();
+            let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i.clone())).unwrap();
+            let z_i1 = circuit
+                .generate_step_constraints(cs.clone(), 1, z_iVar, step)
+                .unwrap()
+                .value()
+                .unwrap();
+            assert_eq!(cs.is_satisfied().unwrap(), satisfied);
+            if satisfied {
+                assert_eq!(z_i1[10..12], [Fr::from(100_u32), Fr::from(150_u32)]);
+            }
+        }
+    }
+
+    // test that the weights and labels of the vouches are accumulated in the state, and that they
+    // are bound to the signature
+    #[test]
+    fn test_fcircuit_attributes() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        type FC = EthDosCircuit<Fr, EdwardsProjective, EdwardsVar>;
+        let circuit = FC::new(circuit_params(&poseidon_config, DEFAULT_CONTEXT)).unwrap();
+        let revocations = RevocationTree::new(&poseidon_config);
+
+        let sk_0 =
+            SigningKey::<EdwardsProjective>::generate::<blake2::Blake2b512>(&mut OsRng).unwrap();
+        let sk_1 =
+            SigningKey::<EdwardsProjective>::generate::<blake2::Blake2b512>(&mut OsRng).unwrap();
+        let (pk_0, pk_1) = (*sk_0.public_key(), *sk_1.public_key());
+        let non_revocation = revocations.non_revocation(pk_0, pk_1).unwrap();
+        let sign = |attributes| {
+            sign_vouch(
+                &sk_1,
+                &poseidon_config,
+                circuit.domain,
+                pk_0,
+                None,
+                attributes,
+            )
+            .unwrap()
+        };
+
+        // chain of weight 4 with the labels 0 and 2
+        let z_i: Vec<Fr> = [
+            vec![pk_0.0.x, pk_0.0.y, pk_0.0.x, pk_0.0.y, Fr::from(2_u32)],
+            vec![Fr::zero(); 4],
+            vec![revocations.root(), Fr::zero(), Fr::from(NO_EXPIRY)],
+            vec![Fr::from(4_u32), Fr::from(0b101_u32)],
+        ]
+        .concat();
+
+        let labelled = sign(Some(Attributes::new(3, 10).unwrap()));
+        let mut tampered = labelled;
+        tampered.attributes = Some(Attributes::new(3, 1).unwrap());
+        let mut without_attributes = labelled;
+        without_attributes.attributes = None;
+        for (sig_pk, satisfied, weight, labels) in [
+            (labelled, true, 14_u32, 0b1101_u32),
+            (sign(None), true, 5, 0b101),
+            (sign(Some(Attributes::new(2, 0).unwrap())), true, 4, 0b101),
+            (tampered, false, 0, 0),
+            (without_attributes, false, 0, 0),
+        ] {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let step =
+                EthDosStepVar::<EdwardsProjective, EdwardsVar>::new_witness(cs.clone(), || {
+                    Ok(EthDosStep::vouch(sig_pk, non_revocation.clone()))
+                })
+                .unwrap();
+            let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i.clone())).unwrap();
+            let z_i1 = circuit
+                .generate_step_constraints(cs.clone(), 2, z_iVar, step)
+                .unwrap()
+                .value()
+                .unwrap();
+            assert_eq!(cs.is_satisfied().unwrap(), satisfied);
+            if satisfied {
+                assert_eq!(z_i1[12..], [Fr::from(weight), Fr::from(labels)]);
+            }
+        }
+    }
+
+    // returns whether the step is satisfied, and if `z_i1` is given, whether the next state
+    // computed by the circuit is `z_i1`. The allocation of invalid points may already fail
+    fn step_satisfied(
+        circuit: &EthDosCircuit<Fr, EdwardsProjective, EdwardsVar>,
+        z_i: &[Fr],
+        step: EthDosStep<EdwardsProjective>,
+        z_i1: Option<&[Fr]>,
+    ) -> bool {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let step =
+            match EthDosStepVar::<EdwardsProjective, EdwardsVar>::new_witness(cs.clone(), || {
+                Ok(step)
+            }) {
+                Ok(step) => step,
+                Err(_) => return false,
+            };
+        let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i.to_vec())).unwrap();
+        let computed_z_i1 = match circuit.generate_step_constraints(cs.clone(), 1, z_iVar, step) {
+            Ok(z_i1) => z_i1,
+            Err(_) => return false,
+        };
+        if let Some(z_i1) = z_i1 {
+            let z_i1Var = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i1.to_vec())).unwrap();
+            computed_z_i1.enforce_equal(&z_i1Var).unwrap();
+        }
+        cs.is_satisfied().unwrap()
+    }
+
+    // test that the step is not satisfied for invalid signatures, forged signatures of small order
+    // public keys, and next states that do not follow from the step
+    #[test]
+    fn test_fcircuit_negative() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        type FC = EthDosCircuit<Fr, EdwardsProjective, EdwardsVar>;
+        let circuit = FC::new(circuit_params(&poseidon_config, DEFAULT_CONTEXT)).unwrap();
+        let revocations = RevocationTree::new(&poseidon_config);
+
+        let sks: Vec<SigningKey<EdwardsProjective>> = (0..3)
+            .map(|_| {
+                SigningKey::<EdwardsProjective>::generate::<blake2::Blake2b512>(&mut OsRng).unwrap()
+            })
+            .collect();
+        let (pk_0, pk_1, pk_2) = (
+            *sks[0].public_key(),
+            *sks[1].public_key(),
+            *sks[2].public_key(),
+        );
+        let sign = |sk, prev_pk| sign_pk(sk, &poseidon_config, circuit.domain, prev_pk).unwrap();
+        let sig_pk = sign(&sks[1], pk_0);
+        // in the empty tree, the non-membership proofs are the same for any key
+        let non_revocation = revocations.non_revocation(pk_0, pk_1).unwrap();
+        let step = |sig_pk| EthDosStep::vouch(sig_pk, non_revocation.clone());
+
+        // state after the first step, where pk_0 signed itself
+        let z_i: Vec<Fr> = [
+            vec![pk_0.0.x, pk_0.0.y, pk_0.0.x, pk_0.0.y, Fr::from(1_u32)],
+            vec![Fr::zero(); 4],
+            vec![revocations.root(), Fr::zero(), Fr::from(NO_EXPIRY)],
+            vec![Fr::from(1_u32), Fr::from(1_u32)],
+        ]
+        .concat();
+        let z_i1: Vec<Fr> = [
+            vec![pk_0.0.x, pk_0.0.y, pk_1.0.x, pk_1.0.y, Fr::from(2_u32)],
+            vec![Fr::zero(); 4],
+            vec![revocations.root(), Fr::zero(), Fr::from(NO_EXPIRY)],
+            vec![Fr::from(2_u32), Fr::from(1_u32)],
+        ]
+        .concat();
+        assert!(step_satisfied(&circuit, &z_i, step(sig_pk), Some(&z_i1)));
+
+        // signature over a different public key than pk_i
+        assert!(!step_satisfied(
+            &circuit,
+            &z_i,
+            step(sign(&sks[1], pk_2)),
+            None
+        ));
+
+        // signature by a different key than the public key of the step
+        let mut other_signer = sign(&sks[2], pk_0);
+        other_signer.pk = pk_1;
+        assert!(!step_satisfied(&circuit, &z_i, step(other_signer), None));
+
+        // tampered s
+        let mut tampered = sig_pk;
+        tampered.sig.s = tampered.sig.s.double();
+        assert!(!step_satisfied(&circuit, &z_i, step(tampered), None));
+
+        // the identity and the points of small order as public key, with R = s*G, which satisfy
+        // the signature equation s*G = R + c*pk for any message (or half of the messages)
+        type Affine = <EdwardsProjective as CurveGroup>::Affine;
+        let order_2 = Affine::new_unchecked(Fr::zero(), -Fr::one());
+        for pk in [EdwardsProjective::zero().into_affine(), order_2] {
+            let mut forged = sig_pk;
+            forged.pk.0 = pk;
+            forged.sig.r = (EdwardsProjective::generator() * forged.sig.s).into_affine();
+            assert!(!step_satisfied(&circuit, &z_i, step(forged), None));
+        }
+        // and as the R of the signature
+        for r in [EdwardsProjective::zero().into_affine(), order_2] {
+            let mut tampered = sig_pk;
+            tampered.sig.r = r;
+            assert!(!step_satisfied(&circuit, &z_i, step(tampered), None));
+        }
+
+        // next state with a degree that does not increment
+        let mut same_degree = z_i1.clone();
+        same_degree[4] = z_i[4];
+        assert!(!step_satisfied(
+            &circuit,
+            &z_i,
+            step(sig_pk),
+            Some(&same_degree)
+        ));
+
+        // next state with pk_0 altered
+        let mut other_pk_0 = z_i1;
+        other_pk_0[0] = pk_2.0.x;
+        other_pk_0[1] = pk_2.0.y;
+        assert!(!step_satisfied(
+            &circuit,
+            &z_i,
+            step(sig_pk),
+            Some(&other_pk_0)
+        ));
     }
 }
@@ -0,0 +1,385 @@
+//! This file contains the envelope format of the serialized artefacts: a header (magic, version,
+//! kind, scheme, flags, circuit digest, domain, params id) followed by the payload.
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use blake2::{Blake2s256, Digest};
+use std::convert::TryInto;
+
+use crate::error::EthDosError;
+
+pub const MAGIC: [u8; 4] = *b"EDOS";
+pub const FORMAT_VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 4 + 1 + 1 + 1 + 1 + 32 + 32 + 32;
+
+/// Domain of the artefacts that do not depend on the domain of the vouches (the revocation trees)
+pub const NO_DOMAIN: [u8; 32] = [0u8; 32];
+
+/// Params id of the artefacts that do not depend on the params (the signatures and revocation
+/// trees)
+pub const NO_PARAMS: [u8; 32] = [0u8; 32];
+
+/// Version of the EthDosCircuit constraints. It has to be increased each time that the circuit
+/// changes, so that the params and proofs generated with the previous circuit are rejected.
+pub const CIRCUIT_VERSION: u32 = 1;
+
+const CURVE_CYCLE: &str = "bn254/grumpkin";
+const SONOBE_REV: &str = "c6f1a246e0705582a75de6becf4ad21f325fa5a1";
+
+// flags
+const FLAG_LZ4: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    ProverParams = 1,
+    VerifierParams = 2,
+    SigPk = 3,
+    IVCProof = 4,
+    Revocations = 5,
+    DeciderProverParams = 6,
+    DeciderVerifierParams = 7,
+    CompressedProof = 8,
+}
+impl Kind {
+    fn from_u8(v: u8) -> Result<Self, EthDosError> {
+        match v {
+            1 => Ok(Kind::ProverParams),
+            2 => Ok(Kind::VerifierParams),
+            3 => Ok(Kind::SigPk),
+            4 => Ok(Kind::IVCProof),
+            5 => Ok(Kind::Revocations),
+            6 => Ok(Kind::DeciderProverParams),
+            7 => Ok(Kind::DeciderVerifierParams),
+            8 => Ok(Kind::CompressedProof),
+            _ => Err(EthDosError::Format(format!("unknown kind {}", v))),
+        }
+    }
+
+    /// Maximum size of the decompressed payload of the artefacts of this kind, so that the size
+    /// prefix of a malformed compressed payload is rejected before allocating it
+    pub fn max_payload_len(&self) -> usize {
+        match self {
+            Kind::ProverParams | Kind::VerifierParams => 1 << 29,
+            Kind::IVCProof => 1 << 27,
+            Kind::SigPk => 1 << 10,
+            Kind::Revocations => 1 << 28,
+            Kind::DeciderProverParams => 1 << 30,
+            Kind::DeciderVerifierParams | Kind::CompressedProof => 1 << 20,
+        }
+    }
+}
+
+/// Folding scheme of the artefact, `Unspecified` for the signatures and revocation trees, and
+/// `Decider` for the 'compress' mode (see `decider.rs`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scheme {
+    Unspecified = 0,
+    Nova = 1,
+    HyperNova = 2,
+    ProtoGalaxy = 3,
+    Decider = 4,
+}
+impl Scheme {
+    fn from_u8(v: u8) -> Result<Self, EthDosError> {
+        match v {
+            0 => Ok(Scheme::Unspecified),
+            1 => Ok(Scheme::Nova),
+            2 => Ok(Scheme::HyperNova),
+            3 => Ok(Scheme::ProtoGalaxy),
+            4 => Ok(Scheme::Decider),
+            _ => Err(EthDosError::Format(format!("unknown folding scheme {}", v))),
+        }
+    }
+}
+impl std::str::FromStr for Scheme {
+    type Err = EthDosError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nova" => Ok(Scheme::Nova),
+            "hypernova" => Ok(Scheme::HyperNova),
+            "protogalaxy" => Ok(Scheme::ProtoGalaxy),
+            _ => Err(EthDosError::Format(format!(
+                "unknown folding scheme '{}', expected nova, hypernova or protogalaxy",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    pub version: u8,
+    pub kind: Kind,
+    pub scheme: Scheme,
+    pub compressed: bool,
+    pub circuit_digest: [u8; 32],
+    pub domain: [u8; 32],
+    pub params_id: [u8; 32],
+}
+
+/// Digest of the circuit configuration
+pub fn circuit_digest<F: PrimeField>(
+    poseidon_config: &PoseidonConfig<F>,
+    state_len: usize,
+) -> Result<[u8; 32], EthDosError> {
+    let mut h = Blake2s256::new();
+    h.update(CIRCUIT_VERSION.to_le_bytes());
+    h.update((state_len as u64).to_le_bytes());
+    h.update(CURVE_CYCLE.as_bytes());
+    h.update(SONOBE_REV.as_bytes());
+
+    h.update((poseidon_config.full_rounds as u64).to_le_bytes());
+    h.update((poseidon_config.partial_rounds as u64).to_le_bytes());
+    h.update(poseidon_config.alpha.to_le_bytes());
+    h.update((poseidon_config.rate as u64).to_le_bytes());
+    h.update((poseidon_config.capacity as u64).to_le_bytes());
+    let mut b = vec![];
+    poseidon_config.ark.serialize_compressed(&mut b)?;
+    poseidon_config.mds.serialize_compressed(&mut b)?;
+    h.update(&b);
+
+    Ok(h.finalize().into())
+}
+
+/// Identifier of the params of the given circuit, folding scheme and domain, computed over their
+/// serialized commitment params, so that the prover and verifier params have the same id.
+pub fn params_id(
+    circuit_digest: [u8; 32],
+    scheme: Scheme,
+    domain: [u8; 32],
+    commitment_params: &[u8],
+) -> [u8; 32] {
+    let mut h = Blake2s256::new();
+    h.update(b"ethdos-fold/params-id");
+    h.update(circuit_digest);
+    h.update([scheme as u8]);
+    h.update(domain);
+    h.update(commitment_params);
+    h.finalize().into()
+}
+
+/// Seed of the canonical params of the given circuit, folding scheme and domain. It is public and
+/// only depends on the circuit config, so that anyone can regenerate the same params.
+pub fn canonical_seed(circuit_digest: [u8; 32], scheme: Scheme, domain: [u8; 32]) -> [u8; 32] {
+    let mut h = Blake2s256::new();
+    h.update(b"ethdos-fold/canonical-params");
+    h.update(circuit_digest);
+    h.update([scheme as u8]);
+    h.update(domain);
+    h.finalize().into()
+}
+
+/// Prepends the header to the payload, compressing the payload with lz4 if `compress` is set.
+pub fn seal(
+    kind: Kind,
+    scheme: Scheme,
+    circuit_digest: [u8; 32],
+    domain: [u8; 32],
+    params_id: [u8; 32],
+    compress: bool,
+    payload: &[u8],
+) -> Vec<u8> {
+    let flags = if compress { FLAG_LZ4 } else { 0 };
+    let payload = if compress {
+        lz4_flex::block::compress_prepend_size(payload)
+    } else {
+        payload.to_vec()
+    };
+    [
+        MAGIC.to_vec(),
+        vec![FORMAT_VERSION, kind as u8, scheme as u8, flags],
+        circuit_digest.to_vec(),
+        domain.to_vec(),
+        params_id.to_vec(),
+        payload,
+    ]
+    .concat()
+}
+
+/// Parses the header, without checking it.
+pub fn read_header(b: &[u8]) -> Result<Header, EthDosError> {
+    if b.len() < HEADER_LEN || b[..4] != MAGIC {
+        return Err(EthDosError::Format(
+            "missing header, the file may be from a version previous to the envelope format"
+                .to_string(),
+        ));
+    }
+    Ok(Header {
+        version: b[4],
+        kind: Kind::from_u8(b[5])?,
+        scheme: Scheme::from_u8(b[6])?,
+        compressed: b[7] & FLAG_LZ4 != 0,
+        circuit_digest: b[8..40]
+            .try_into()
+            .map_err(|_| EthDosError::Format("invalid circuit digest".to_string()))?,
+        domain: b[40..72]
+            .try_into()
+            .map_err(|_| EthDosError::Format("invalid domain".to_string()))?,
+        params_id: b[72..HEADER_LEN]
+            .try_into()
+            .map_err(|_| EthDosError::Format("invalid params id".to_string()))?,
+    })
+}
+
+/// Checks the header against the expected values and returns the (decompressed) payload. The
+/// params id is not checked, the functions at `backend.rs` check it.
+pub fn open(
+    kind: Kind,
+    scheme: Scheme,
+    circuit_digest: [u8; 32],
+    domain: [u8; 32],
+    b: &[u8],
+) -> Result<Vec<u8>, EthDosError> {
+    let header = read_header(b)?;
+    if header.version != FORMAT_VERSION {
+        return Err(EthDosError::Format(format!(
+            "unsupported format version {}, expected {}",
+            header.version, FORMAT_VERSION
+        )));
+    }
+    if header.kind != kind {
+        return Err(EthDosError::Format(format!(
+            "expected {:?}, got {:?}",
+            kind, header.kind
+        )));
+    }
+    if header.scheme != scheme {
+        return Err(EthDosError::Format(format!(
+            "expected folding scheme {:?}, got {:?}",
+            scheme, header.scheme
+        )));
+    }
+    if header.circuit_digest != circuit_digest {
+        return Err(EthDosError::Format(
+            "circuit digest mismatch, the file was generated for a different circuit \
+             version or config, params need to be regenerated and proofs re-folded"
+                .to_string(),
+        ));
+    }
+    if header.domain != domain {
+        return Err(EthDosError::Format(
+            "domain mismatch, the file was generated for a different domain".to_string(),
+        ));
+    }
+
+    let payload = &b[HEADER_LEN..];
+    if header.compressed {
+        decompress(kind, payload)
+    } else {
+        Ok(payload.to_vec())
+    }
+}
+
+// decompresses the lz4 payload, checking its size prefix against the maximum of its kind before
+// allocating the output
+fn decompress(kind: Kind, payload: &[u8]) -> Result<Vec<u8>, EthDosError> {
+    if payload.len() < 4 {
+        return Err(EthDosError::Format(
+            "missing size of the compressed payload".to_string(),
+        ));
+    }
+    let (size, compressed) = payload.split_at(4);
+    let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize;
+    if size > kind.max_payload_len() {
+        return Err(EthDosError::Format(format!(
+            "the size of the decompressed {:?} ({} bytes) exceeds the maximum of {} bytes",
+            kind,
+            size,
+            kind.max_payload_len()
+        )));
+    }
+    let mut out = vec![0u8; size];
+    let n = lz4_flex::block::decompress_into(compressed, &mut out)?;
+    if n != size {
+        return Err(EthDosError::Format(format!(
+            "decompressed {} bytes, expected {}",
+            n, size
+        )));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+
+    const DOMAIN: [u8; 32] = [7u8; 32];
+    const PARAMS_ID: [u8; 32] = [9u8; 32];
+
+    #[test]
+    fn test_envelope() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let digest = circuit_digest(&poseidon_config, 5).unwrap();
+        assert_ne!(digest, circuit_digest(&poseidon_config, 6).unwrap());
+
+        let payload = vec![42u8; 1000];
+        for compress in [false, true] {
+            let b = seal(
+                Kind::IVCProof,
+                Scheme::Nova,
+                digest,
+                DOMAIN,
+                PARAMS_ID,
+                compress,
+                &payload,
+            );
+            assert_eq!(read_header(&b).unwrap().compressed, compress);
+            assert_eq!(read_header(&b).unwrap().params_id, PARAMS_ID);
+            assert_eq!(
+                open(Kind::IVCProof, Scheme::Nova, digest, DOMAIN, &b).unwrap(),
+                payload
+            );
+
+            // wrong kind
+            assert!(open(Kind::SigPk, Scheme::Nova, digest, DOMAIN, &b).is_err());
+            // wrong domain
+            assert!(open(Kind::IVCProof, Scheme::Nova, digest, NO_DOMAIN, &b).is_err());
+            // wrong circuit
+            assert!(open(Kind::IVCProof, Scheme::Nova, [0u8; 32], DOMAIN, &b).is_err());
+            // a compressed payload claiming a decompressed size above the maximum of its kind is
+            // rejected before allocating it
+            if compress {
+                let mut b = b.clone();
+                b[HEADER_LEN..HEADER_LEN + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+                assert!(open(Kind::IVCProof, Scheme::Nova, digest, DOMAIN, &b).is_err());
+            }
+            // unsupported version
+            let mut b = b;
+            b[4] = FORMAT_VERSION + 1;
+            assert!(open(Kind::IVCProof, Scheme::Nova, digest, DOMAIN, &b).is_err());
+        }
+
+        // wrong folding scheme
+        let b = seal(
+            Kind::IVCProof,
+            Scheme::HyperNova,
+            digest,
+            DOMAIN,
+            PARAMS_ID,
+            false,
+            &payload,
+        );
+        assert!(open(Kind::IVCProof, Scheme::Nova, digest, DOMAIN, &b).is_err());
+        assert_eq!(
+            "ProtoGalaxy".parse::<Scheme>().unwrap(),
+            Scheme::ProtoGalaxy
+        );
+        assert!("groth16".parse::<Scheme>().is_err());
+
+        // the params id depends on the folding scheme, domain and commitment params
+        let id = params_id(digest, Scheme::Nova, DOMAIN, &payload);
+        assert_ne!(id, params_id(digest, Scheme::HyperNova, DOMAIN, &payload));
+        assert_ne!(id, params_id(digest, Scheme::Nova, NO_DOMAIN, &payload));
+        assert_ne!(id, params_id(digest, Scheme::Nova, DOMAIN, &payload[1..]));
+        assert_ne!(
+            canonical_seed(digest, Scheme::Nova, DOMAIN),
+            canonical_seed(digest, Scheme::Nova, NO_DOMAIN)
+        );
+
+        // headerless (legacy) bytes
+        assert!(open(Kind::IVCProof, Scheme::Nova, digest, DOMAIN, &payload).is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! This file contains the graph of signatures between public keys, from which the chain of
+//! signatures to be folded is obtained. Each signature of `pk_{i+1}` over `pk_i` is an edge
+//! `pk_i <- pk_{i+1}`, and the chain proving the degree of distance of a public key from `pk_0` is
+//! the shortest path between them through the vouches that are valid and not revoked.
+use ark_crypto_primitives::sponge::{poseidon::PoseidonConfig, Absorb};
+use ark_ec::CurveGroup;
+use ark_ff::PrimeField;
+use std::collections::{HashMap, VecDeque};
+
+use arkeddsa::{ed_on_bn254_twist::EdwardsProjective, PublicKey};
+
+use crate::error::EthDosError;
+use crate::revocation::RevocationTree;
+use crate::signature::{verify_sig_pk, SigPk};
+
+/// Directed graph of the signatures between public keys of a domain, where the edges are verified
+/// when added.
+#[derive(Clone, Debug)]
+pub struct SignatureGraph<C: CurveGroup> {
+    poseidon_config: PoseidonConfig<C::BaseField>,
+    domain: C::BaseField,
+    // signatures over each public key (indexed by its bytes), ie. the edges going out of it
+    vouches: HashMap<Vec<u8>, Vec<SigPk<C>>>,
+    n_edges: usize,
+}
+
+impl<C: CurveGroup> SignatureGraph<C>
+where
+    C::BaseField: PrimeField + Absorb,
+{
+    pub fn new(poseidon_config: PoseidonConfig<C::BaseField>, domain: C::BaseField) -> Self {
+        Self {
+            poseidon_config,
+            domain,
+            vouches: HashMap::new(),
+            n_edges: 0,
+        }
+    }
+
+    /// Number of (distinct) signatures in the graph
+    pub fn len(&self) -> usize {
+        self.n_edges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n_edges == 0
+    }
+
+    /// Adds the edge `prev_pk <- sig_pk.pk`, after checking that `sig_pk.sig` is a valid signature
+    /// of `sig_pk.pk` over `prev_pk` in the domain of the graph. A signature of a public key over
+    /// itself is the edge from which the chains starting at that public key begin. All the
+    /// distinct vouches of `sig_pk.pk` for `prev_pk` (eg. a vouch and its renewals) are kept, and
+    /// the one used is chosen by `path`.
+    pub fn add(&mut self, prev_pk: PublicKey<C>, sig_pk: SigPk<C>) -> Result<(), EthDosError> {
+        verify_sig_pk(&self.poseidon_config, self.domain, prev_pk, &sig_pk)?;
+
+        let edges = self.vouches.entry(prev_pk.to_bytes()).or_default();
+        let duplicated = edges.iter().any(|e| {
+            e.pk == sig_pk.pk && e.validity == sig_pk.validity && e.attributes == sig_pk.attributes
+        });
+        if !duplicated {
+            edges.push(sig_pk);
+            self.n_edges += 1;
+        }
+        Ok(())
+    }
+
+    /// Adds a chain of signatures as the one used for folding, where the first element is the
+    /// signature of `pk_0` over itself and each next one is over the public key of the previous.
+    pub fn add_chain(&mut self, pks_sigs: &[SigPk<C>]) -> Result<(), EthDosError> {
+        let mut prev_pk = match pks_sigs.first() {
+            Some(first) => first.pk,
+            None => return Ok(()),
+        };
+        for sig_pk in pks_sigs {
+            self.add(prev_pk, *sig_pk)?;
+            prev_pk = sig_pk.pk;
+        }
+        Ok(())
+    }
+}
+
+impl SignatureGraph<EdwardsProjective> {
+    /// Returns the shortest chain of signatures from `root` to `target` whose vouches are valid at
+    /// `now` (in seconds since the unix epoch) and not revoked in `revocations`, ready to be
+    /// folded: the first element is the signature of `root` over itself, and the last one is the
+    /// signature of `target`. The degree of the resulting proof is the length of the returned
+    /// chain. When a public key has several vouches for the same one, the usable one expiring the
+    /// latest is chosen.
+    pub fn path(
+        &self,
+        root: PublicKey<EdwardsProjective>,
+        target: PublicKey<EdwardsProjective>,
+        now: u64,
+        revocations: &RevocationTree,
+    ) -> Result<Vec<SigPk<EdwardsProjective>>, EthDosError> {
+        let root_bytes = root.to_bytes();
+        let target_bytes = target.to_bytes();
+        // the vouches that can be folded at `now` with the given revocation tree
+        let usable = |prev_pk: PublicKey<EdwardsProjective>, edge: &SigPk<EdwardsProjective>| {
+            edge.validity.unwrap_or_default().contains(now)
+                && revocations.non_revocation(prev_pk, edge.pk).is_ok()
+        };
+
+        let root_edges = self.vouches.get(&root_bytes).map_or(&[][..], |e| &e[..]);
+        if !root_edges.iter().any(|e| e.pk == root) {
+            return Err(EthDosError::Graph(
+                "missing the signature of the root over itself".to_string(),
+            ));
+        }
+        let self_sig = latest_usable(root_edges, root, root, &usable).ok_or_else(|| {
+            EthDosError::Graph(
+                "the signature of the root over itself is expired or revoked".to_string(),
+            )
+        })?;
+
+        // breadth-first search from the root through the usable vouches, storing for each reached
+        // public key the edge through which it was reached
+        let mut reached_by: HashMap<Vec<u8>, (Vec<u8>, SigPk<EdwardsProjective>)> = HashMap::new();
+        let mut queue = VecDeque::from(vec![(root_bytes.clone(), root)]);
+        while let Some((pk_bytes, pk)) = queue.pop_front() {
+            if pk_bytes == target_bytes {
+                break;
+            }
+            let edges = self.vouches.get(&pk_bytes).map_or(&[][..], |e| &e[..]);
+            for edge in edges {
+                let next = edge.pk.to_bytes();
+                if next == root_bytes || reached_by.contains_key(&next) {
+                    continue;
+                }
+                if let Some(vouch) = latest_usable(edges, pk, edge.pk, &usable) {
+                    reached_by.insert(next.clone(), (pk_bytes.clone(), vouch));
+                    queue.push_back((next, edge.pk));
+                }
+            }
+        }
+        if target_bytes != root_bytes && !reached_by.contains_key(&target_bytes) {
+            return Err(EthDosError::Graph(
+                "no chain of valid and not revoked signatures from the root to the target"
+                    .to_string(),
+            ));
+        }
+
+        // walk back from the target to the root
+        let mut path = vec![];
+        let mut pk = target_bytes;
+        while let Some((prev, edge)) = reached_by.get(&pk) {
+            path.push(*edge);
+            pk = prev.clone();
+        }
+        path.push(self_sig);
+        path.reverse();
+        Ok(path)
+    }
+}
+
+// the vouch of `pk` for `prev_pk` among `edges` (the vouches for `prev_pk`) that is usable and
+// expires the latest
+fn latest_usable<C: CurveGroup>(
+    edges: &[SigPk<C>],
+    prev_pk: PublicKey<C>,
+    pk: PublicKey<C>,
+    usable: impl Fn(PublicKey<C>, &SigPk<C>) -> bool,
+) -> Option<SigPk<C>> {
+    edges
+        .iter()
+        .filter(|e| e.pk == pk && usable(prev_pk, e))
+        .max_by_key(|e| e.validity.unwrap_or_default().not_after)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bn254::Fr;
+    use rand::rngs::OsRng;
+
+    use arkeddsa::{ed_on_bn254_twist::EdwardsProjective, SigningKey};
+    use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+
+    use super::*;
+    use crate::api::{self, tests::Fixture};
+    use crate::backend::NovaFS;
+    use crate::revocation::RevocationTree;
+    use crate::signature::{domain_tag, sign_pk, sign_vouch, Validity};
+    use crate::utils::get_time;
+
+    // builds the graph of the README's diagram:
+    //   pk_0 <- pk_1 <- pk_2 <- pk_3
+    //                   pk_2 <- pk_A <- pk_B
+    //           pk_1 <- pk_beta
+    #[test]
+    fn test_signature_graph() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let domain = api::default_domain();
+        let sks: Vec<SigningKey<EdwardsProjective>> = (0..7)
+            .map(|_| SigningKey::generate::<blake2::Blake2b512>(&mut OsRng).unwrap())
+            .collect();
+        let pks: Vec<PublicKey<EdwardsProjective>> =
+            sks.iter().map(|sk| *sk.public_key()).collect();
+        let (pk_0, pk_1, pk_2, pk_3, pk_a, pk_b, pk_beta) =
+            (pks[0], pks[1], pks[2], pks[3], pks[4], pks[5], pks[6]);
+        let sig = |signer: usize, prev_pk| {
+            sign_pk(&sks[signer], &poseidon_config, domain, prev_pk).unwrap()
+        };
+
+        let now = get_time() / 1000;
+        let revocations = RevocationTree::new(&poseidon_config);
+        let mut graph = SignatureGraph::<EdwardsProjective>::new(poseidon_config.clone(), domain);
+        graph
+            .add_chain(&[sig(0, pk_0), sig(1, pk_0), sig(2, pk_1), sig(3, pk_2)])
+            .unwrap();
+        graph.add(pk_2, sig(4, pk_2)).unwrap();
+        graph.add(pk_a, sig(5, pk_a)).unwrap();
+        graph.add(pk_1, sig(6, pk_1)).unwrap();
+        assert_eq!(graph.len(), 7);
+        // duplicated signatures are ignored
+        graph.add(pk_0, sig(1, pk_0)).unwrap();
+        assert_eq!(graph.len(), 7);
+
+        // a signature over a different public key is rejected, and so is one of another domain
+        assert!(graph.add(pk_3, sig(3, pk_2)).is_err());
+        let other = sign_pk(&sks[3], &poseidon_config, domain_tag("other"), pk_2).unwrap();
+        assert!(graph.add(pk_2, other).is_err());
+
+        let path = graph.path(pk_0, pk_3, now, &revocations).unwrap();
+        assert_eq!(
+            path.iter().map(|s| s.pk).collect::<Vec<_>>(),
+            vec![pk_0, pk_1, pk_2, pk_3]
+        );
+        assert_eq!(graph.path(pk_0, pk_b, now, &revocations).unwrap().len(), 5);
+        assert_eq!(graph.path(pk_0, pk_0, now, &revocations).unwrap().len(), 1);
+
+        // the shortest path is chosen
+        graph.add(pk_1, sig(5, pk_1)).unwrap();
+        assert_eq!(
+            graph
+                .path(pk_0, pk_b, now, &revocations)
+                .unwrap()
+                .iter()
+                .map(|s| s.pk)
+                .collect::<Vec<_>>(),
+            vec![pk_0, pk_1, pk_b]
+        );
+
+        // unreachable target, and root without signature over itself
+        graph.add(pk_3, sig(3, pk_3)).unwrap();
+        assert!(matches!(
+            graph.path(pk_3, pk_0, now, &revocations),
+            Err(EthDosError::Graph(_))
+        ));
+        assert!(matches!(
+            graph.path(pk_1, pk_2, now, &revocations),
+            Err(EthDosError::Graph(_))
+        ));
+
+        // the path is a valid input for folding
+        let path = graph.path(pk_0, pk_beta, now, &revocations).unwrap();
+        let f = Fixture::new();
+        let proof = f.fold(&path);
+        let state = api::verify_proof_state::<NovaFS>(
+            f.params.1,
+            proof,
+            pk_0,
+            revocations.root(),
+            now,
+            Some(pk_beta),
+            None,
+        )
+        .unwrap();
+        assert_eq!(state.degree, 3);
+    }
+
+    // the paths skip the expired and revoked vouches, even if it makes them longer:
+    //   pk_0 <- pk_1 <- pk_3
+    //   pk_0 <- pk_2 <- pk_4 <- pk_3
+    //           pk_2 <- pk_3 (added later)
+    #[test]
+    fn test_signature_graph_validity() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let domain = api::default_domain();
+        let sks: Vec<SigningKey<EdwardsProjective>> = (0..5)
+            .map(|_| SigningKey::generate::<blake2::Blake2b512>(&mut OsRng).unwrap())
+            .collect();
+        let pks: Vec<PublicKey<EdwardsProjective>> =
+            sks.iter().map(|sk| *sk.public_key()).collect();
+        let vouch = |signer: usize, prev: usize, validity: Option<Validity>| {
+            sign_vouch(
+                &sks[signer],
+                &poseidon_config,
+                domain,
+                pks[prev],
+                validity,
+                None,
+            )
+            .unwrap()
+        };
+        let now = 1_000;
+        let expired = Validity::new(0, now).unwrap();
+        let mut revocations = RevocationTree::new(&poseidon_config);
+
+        let mut graph = SignatureGraph::<EdwardsProjective>::new(poseidon_config.clone(), domain);
+        graph.add(pks[0], vouch(0, 0, None)).unwrap();
+        graph.add(pks[0], vouch(1, 0, None)).unwrap();
+        graph.add(pks[1], vouch(3, 1, Some(expired))).unwrap();
+        graph.add(pks[0], vouch(2, 0, None)).unwrap();
+        graph.add(pks[2], vouch(4, 2, None)).unwrap();
+        graph.add(pks[4], vouch(3, 4, None)).unwrap();
+        let chain =
+            |path: Vec<SigPk<EdwardsProjective>>| path.iter().map(|s| s.pk).collect::<Vec<_>>();
+
+        // the shortest chain goes through an expired vouch, so the longer one is returned
+        assert_eq!(
+            chain(graph.path(pks[0], pks[3], now, &revocations).unwrap()),
+            vec![pks[0], pks[2], pks[4], pks[3]]
+        );
+        assert_eq!(
+            chain(graph.path(pks[0], pks[3], now - 1, &revocations).unwrap()),
+            vec![pks[0], pks[1], pks[3]]
+        );
+
+        // a renewed vouch is used instead of the expired one, which is kept
+        let renewed = Validity::new(now - 1, 2 * now).unwrap();
+        graph.add(pks[1], vouch(3, 1, Some(renewed))).unwrap();
+        assert_eq!(graph.len(), 7);
+        assert_eq!(
+            chain(graph.path(pks[0], pks[3], now, &revocations).unwrap()),
+            vec![pks[0], pks[1], pks[3]]
+        );
+        assert_eq!(
+            graph.path(pks[0], pks[3], now, &revocations).unwrap()[2].validity,
+            Some(renewed)
+        );
+        // and the expired one is still used before the renewal starts
+        assert_eq!(
+            graph.path(pks[0], pks[3], now - 2, &revocations).unwrap()[2].validity,
+            Some(expired)
+        );
+
+        // an expired vouch valid for a long window does not hide a valid one of a shorter window
+        let long = Validity::new(0, now - 10).unwrap();
+        let short = Validity::new(now - 5, now + 5).unwrap();
+        graph.add(pks[2], vouch(3, 2, Some(long))).unwrap();
+        graph.add(pks[2], vouch(3, 2, Some(short))).unwrap();
+        assert_eq!(graph.len(), 9);
+        revocations.revoke_vouch(pks[1], pks[3]).unwrap();
+        let path = graph.path(pks[0], pks[3], now, &revocations).unwrap();
+        assert_eq!(chain(path.clone()), vec![pks[0], pks[2], pks[3]]);
+        assert_eq!(path[2].validity, Some(short));
+        // duplicated vouches are ignored
+        graph.add(pks[2], vouch(3, 2, Some(short))).unwrap();
+        assert_eq!(graph.len(), 9);
+
+        // the revoked vouches and the vouches of revoked public keys are skipped
+        revocations.revoke_vouch(pks[2], pks[3]).unwrap();
+        assert_eq!(
+            chain(graph.path(pks[0], pks[3], now, &revocations).unwrap()),
+            vec![pks[0], pks[2], pks[4], pks[3]]
+        );
+        revocations.revoke_pk(pks[4]).unwrap();
+        assert!(matches!(
+            graph.path(pks[0], pks[3], now, &revocations),
+            Err(EthDosError::Graph(_))
+        ));
+        revocations.revoke_pk(pks[0]).unwrap();
+        assert!(matches!(
+            graph.path(pks[0], pks[2], now, &revocations),
+            Err(EthDosError::Graph(_))
+        ));
+    }
+}
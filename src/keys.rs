@@ -0,0 +1,249 @@
+//! This file contains the key management: generation of the secret keys, their (optionally
+//! password-encrypted) serialization, and the signing of other users' public keys.
+use argon2::Argon2;
+use ark_bn254::Fr;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use rand_core::CryptoRngCore;
+use std::convert::TryInto;
+
+use arkeddsa::{ed_on_bn254_twist::EdwardsProjective, PublicKey, SigningKey};
+use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+
+use crate::error::EthDosError;
+use crate::signature::{self, Attributes, SigPk, Validity};
+
+// serialization flags of the secret key
+const PLAIN: u8 = 0;
+const ENCRYPTED: u8 = 1;
+
+const SEED_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Secret key, from which the arkeddsa `SigningKey` is deterministically derived.
+#[derive(Clone, PartialEq)]
+pub struct SecretKey([u8; SEED_LEN]);
+
+impl SecretKey {
+    pub fn generate<R: CryptoRngCore>(rng: &mut R) -> Self {
+        let mut seed = [0u8; SEED_LEN];
+        rng.fill_bytes(&mut seed);
+        Self(seed)
+    }
+
+    pub fn signing_key(&self) -> Result<SigningKey<EdwardsProjective>, EthDosError> {
+        let mut rng = ChaCha20Rng::from_seed(self.0);
+        SigningKey::<EdwardsProjective>::generate::<blake2::Blake2b512>(&mut rng)
+            .map_err(|_| EthDosError::Key("could not derive the signing key".to_string()))
+    }
+
+    pub fn public_key(&self) -> Result<PublicKey<EdwardsProjective>, EthDosError> {
+        Ok(*self.signing_key()?.public_key())
+    }
+
+    /// Serializes the secret key without encryption
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [vec![PLAIN], self.0.to_vec()].concat()
+    }
+
+    /// Serializes the secret key encrypted with ChaCha20-Poly1305, under a key derived from the
+    /// given password with Argon2.
+    pub fn to_encrypted_bytes<R: CryptoRngCore>(
+        &self,
+        rng: &mut R,
+        password: &str,
+    ) -> Result<Vec<u8>, EthDosError> {
+        let mut salt = [0u8; SALT_LEN];
+        rng.fill_bytes(&mut salt);
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill_bytes(&mut nonce);
+
+        let cipher = cipher(password, &salt)?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), self.0.as_ref())
+            .map_err(|_| EthDosError::Key("could not encrypt the secret key".to_string()))?;
+        Ok([vec![ENCRYPTED], salt.to_vec(), nonce.to_vec(), ciphertext].concat())
+    }
+
+    /// Deserializes the secret key, the password is only needed if it was serialized encrypted.
+    pub fn from_bytes(b: &[u8], password: Option<&str>) -> Result<Self, EthDosError> {
+        let (flag, b) = b
+            .split_first()
+            .ok_or_else(|| EthDosError::Deserialization("empty secret key".to_string()))?;
+        let seed = match *flag {
+            PLAIN => b.to_vec(),
+            ENCRYPTED => {
+                let password = password.ok_or_else(|| {
+                    EthDosError::Key("the secret key is encrypted, password needed".to_string())
+                })?;
+                if b.len() < SALT_LEN + NONCE_LEN {
+                    return Err(EthDosError::Deserialization(
+                        "encrypted secret key too short".to_string(),
+                    ));
+                }
+                let (salt, b) = b.split_at(SALT_LEN);
+                let (nonce, ciphertext) = b.split_at(NONCE_LEN);
+                cipher(password, salt)?
+                    .decrypt(Nonce::from_slice(nonce), ciphertext)
+                    .map_err(|_| {
+                        EthDosError::Key(
+                            "could not decrypt the secret key, wrong password?".to_string(),
+                        )
+                    })?
+            }
+            _ => {
+                return Err(EthDosError::Deserialization(format!(
+                    "unknown secret key flag {}",
+                    flag
+                )))
+            }
+        };
+        let seed: [u8; SEED_LEN] = seed.try_into().map_err(|_| {
+            EthDosError::Deserialization(format!("secret key must be {} bytes", SEED_LEN))
+        })?;
+        Ok(Self(seed))
+    }
+
+    /// Returns true if the serialized secret key is encrypted
+    pub fn is_encrypted(b: &[u8]) -> bool {
+        b.first() == Some(&ENCRYPTED)
+    }
+}
+
+fn cipher(password: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, EthDosError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| EthDosError::Key(format!("key derivation: {}", e)))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+/// Generates a new secret key and returns it together with its public key
+pub fn keygen<R: CryptoRngCore>(
+    rng: &mut R,
+) -> Result<(SecretKey, PublicKey<EdwardsProjective>), EthDosError> {
+    let sk = SecretKey::generate(rng);
+    let pk = sk.public_key()?;
+    Ok((sk, pk))
+}
+
+/// Signs the given public key, ie. the owner of `sk` vouches for `prev_pk`. The returned `SigPk`
+/// is the external input of the folding step that goes from `prev_pk` to the public key of `sk`,
+/// and it is only valid for the chains of the given domain (see `api::default_domain`).
+pub fn sign_pk(
+    sk: &SecretKey,
+    domain: Fr,
+    prev_pk: PublicKey<EdwardsProjective>,
+) -> Result<SigPk<EdwardsProjective>, EthDosError> {
+    let poseidon_config = poseidon_canonical_config::<Fr>();
+    signature::sign_pk(&sk.signing_key()?, &poseidon_config, domain, prev_pk)
+}
+
+/// Same as `sign_pk`, but the vouch is only valid within the given validity window, see
+/// `signature::Validity`.
+pub fn sign_pk_with_validity(
+    sk: &SecretKey,
+    domain: Fr,
+    prev_pk: PublicKey<EdwardsProjective>,
+    validity: Validity,
+) -> Result<SigPk<EdwardsProjective>, EthDosError> {
+    let poseidon_config = poseidon_canonical_config::<Fr>();
+    signature::sign_pk_with_validity(
+        &sk.signing_key()?,
+        &poseidon_config,
+        domain,
+        prev_pk,
+        Some(validity),
+    )
+}
+
+/// Same as `sign_pk`, with an optional validity window and optional attributes (label and weight)
+/// of the vouch, see `signature::Attributes`.
+pub fn sign_vouch(
+    sk: &SecretKey,
+    domain: Fr,
+    prev_pk: PublicKey<EdwardsProjective>,
+    validity: Option<Validity>,
+    attributes: Option<Attributes>,
+) -> Result<SigPk<EdwardsProjective>, EthDosError> {
+    let poseidon_config = poseidon_canonical_config::<Fr>();
+    signature::sign_vouch(
+        &sk.signing_key()?,
+        &poseidon_config,
+        domain,
+        prev_pk,
+        validity,
+        attributes,
+    )
+}
+
+/// Signs the verifier's challenge, proving the ownership of the last public key of a chain in the
+/// presentation step (see `decider::present`).
+pub fn sign_challenge(
+    sk: &SecretKey,
+    challenge: Fr,
+) -> Result<SigPk<EdwardsProjective>, EthDosError> {
+    let poseidon_config = poseidon_canonical_config::<Fr>();
+    signature::sign_challenge(&sk.signing_key()?, &poseidon_config, challenge)
+}
+
+/// Signs the verifier's challenge, proving natively the ownership of the public key of `sk` (see
+/// `api::prove_ownership`).
+pub fn sign_ownership(
+    sk: &SecretKey,
+    challenge: Fr,
+) -> Result<SigPk<EdwardsProjective>, EthDosError> {
+    let poseidon_config = poseidon_canonical_config::<Fr>();
+    signature::sign_ownership(&sk.signing_key()?, &poseidon_config, challenge)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::signature::{domain_tag, hash_pk, DEFAULT_CONTEXT};
+
+    #[test]
+    fn test_keys() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let domain = domain_tag::<Fr>(DEFAULT_CONTEXT);
+
+        let (sk_0, pk_0) = keygen(&mut OsRng).unwrap();
+        let (sk_1, pk_1) = keygen(&mut OsRng).unwrap();
+
+        // plain serialization
+        let b = sk_1.to_bytes();
+        assert!(!SecretKey::is_encrypted(&b));
+        let sk_1_parsed = SecretKey::from_bytes(&b, None).unwrap();
+        assert!(sk_1_parsed == sk_1);
+        assert_eq!(sk_1_parsed.public_key().unwrap(), pk_1);
+
+        // encrypted serialization
+        let b = sk_1.to_encrypted_bytes(&mut OsRng, "password").unwrap();
+        assert!(SecretKey::is_encrypted(&b));
+        assert!(SecretKey::from_bytes(&b, None).is_err());
+        assert!(SecretKey::from_bytes(&b, Some("wrong")).is_err());
+        let sk_1_parsed = SecretKey::from_bytes(&b, Some("password")).unwrap();
+        assert!(sk_1_parsed == sk_1);
+
+        // pk_1 vouches for pk_0
+        let sig_pk = sign_pk(&sk_1_parsed, domain, pk_0).unwrap();
+        assert_eq!(sig_pk.pk, pk_1);
+        let msg = hash_pk(&poseidon_config, domain, pk_0).unwrap();
+        sig_pk
+            .pk
+            .verify(&poseidon_config, &msg, &sig_pk.sig)
+            .unwrap();
+
+        // the signature of pk_0 over itself, the first step of a chain
+        let sig_pk = sign_pk(&sk_0, domain, pk_0).unwrap();
+        let msg = hash_pk(&poseidon_config, domain, pk_0).unwrap();
+        pk_0.verify(&poseidon_config, &msg, &sig_pk.sig).unwrap();
+    }
+}
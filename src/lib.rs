@@ -4,215 +4,583 @@
 #![allow(dead_code)]
 #![allow(non_camel_case_types)]
 #![allow(clippy::upper_case_acronyms)]
+// the verifier-only build (without the `prover` feature) leaves some imports unused
+#![cfg_attr(not(feature = "prover"), allow(unused_imports))]
 
-use ark_bn254::{Fr, G1Projective as G1};
-use ark_ec::AffineRepr;
-use ark_grumpkin::Projective as G2;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::Zero;
+use ark_bn254::Fr;
 use base64::{engine::general_purpose::STANDARD as b64, Engine as _};
 use rand::rngs::OsRng;
 
-use arkeddsa::ed_on_bn254_twist::{constraints::EdwardsVar, EdwardsProjective};
+use arkeddsa::ed_on_bn254_twist::EdwardsProjective;
+use arkeddsa::PublicKey;
 
-use folding_schemes::{
-    commitment::pedersen::Pedersen,
-    folding::nova::{Nova, PreprocessorParam},
-    frontend::FCircuit,
-    transcript::poseidon::poseidon_canonical_config,
-    FoldingScheme,
-};
-
-use crate::fcircuit::EthDosCircuit;
-use crate::signature::{gen_signatures, SigPk};
-use crate::utils::{dbg, elapsed, get_time};
+use folding_schemes::transcript::poseidon::poseidon_canonical_config;
 
-mod fcircuit;
-mod signature;
+use crate::api::ProofState;
+use crate::error::EthDosError;
+use crate::format::Scheme;
+use crate::revocation::RevocationTree;
+use crate::signature::{
+    domain_tag, gen_signatures, pk_from_bytes, Attributes, SigPk, Validity, DEFAULT_WEIGHT,
+    NO_EXPIRY, NO_LABEL,
+};
+use crate::utils::dbg;
+
+pub mod api;
+pub mod backend;
+pub mod cache;
+#[cfg(feature = "decider")]
+pub mod decider;
+pub mod error;
+pub mod fcircuit;
+pub mod format;
+pub mod graph;
+pub mod keys;
+pub mod revocation;
+pub mod signature;
 mod utils;
 
 use wasm_bindgen::prelude::*;
 
-// define type aliases for the FCircuit (FC) and the FoldingScheme (FS), to avoid writing the whole
-// type each time.
-type FC = EthDosCircuit<Fr, EdwardsProjective, EdwardsVar>;
-type FS = Nova<G1, G2, FC, Pedersen<G1>, Pedersen<G2>>;
-
 #[wasm_bindgen]
 extern "C" {
     fn alert(s: &str);
 }
 
+/// Initializes the pool of `n_threads` web workers over which the folding runs in parallel, in the
+/// `wasm-threads` build (see the README). It has to be awaited once before the rest of the
+/// methods, which have to be called from a web worker, since they block waiting for the pool. In
+/// the single-threaded build it does nothing, so the same JS works with both builds.
 #[wasm_bindgen]
-pub fn gen_params() -> Vec<String> {
-    let mut rng = OsRng;
-    let poseidon_config = poseidon_canonical_config::<Fr>();
+pub fn init_thread_pool(n_threads: usize) -> js_sys::Promise {
+    #[cfg(all(feature = "wasm-threads", target_arch = "wasm32"))]
+    return wasm_bindgen_rayon::init_thread_pool(n_threads);
 
-    let f_circuit = FC::new(poseidon_config.clone()).unwrap();
+    #[cfg(not(all(feature = "wasm-threads", target_arch = "wasm32")))]
+    {
+        dbg(format!(
+            "single-threaded build, {} threads ignored",
+            n_threads
+        ));
+        js_sys::Promise::resolve(&JsValue::UNDEFINED)
+    }
+}
 
-    let nova_preprocess_params = PreprocessorParam::new(poseidon_config.clone(), f_circuit.clone());
-    let start = get_time();
-    let nova_params = FS::preprocess(&mut rng, &nova_preprocess_params).unwrap();
-    dbg(format!("Nova params generated: {:?}ms", elapsed(start)));
+/// Returns `[prover_params, verifier_params]`, in base64, for the given folding scheme ("nova",
+/// "hypernova" or "protogalaxy", by default "nova") and the domain of the given context (by
+/// default "ethdos"). The rest of the methods use the folding scheme and domain of the given
+/// params. Without a `seed`, the canonical params are generated (see `api::gen_canonical_params`),
+/// which are the same in every session, so they can be cached under `canonical_params_key`. With
+/// a `seed`, the params are generated from it, only for tests (see `api::seeded_rng`).
+#[cfg(feature = "prover")]
+#[wasm_bindgen]
+pub fn gen_params(
+    scheme: Option<String>,
+    context: Option<String>,
+    seed: Option<u64>,
+) -> Result<Vec<String>, JsValue> {
+    let scheme = decode_scheme(scheme.as_deref())?;
+    let domain = decode_domain(context.as_deref());
+    let (pp, vp) = match seed {
+        Some(seed) => backend::setup_with_rng(scheme, domain, &mut api::seeded_rng(seed))?,
+        None => backend::setup_canonical(scheme, domain)?,
+    };
+    Ok(vec![b64.encode(pp), b64.encode(vp)])
+}
 
-    // serialize
-    let start = get_time();
-    let mut prover_params_serialized = vec![];
-    nova_params
-        .0
-        .serialize_compressed(&mut prover_params_serialized)
-        .unwrap();
-    dbg(format!(
-        "Nova prover params serialized: {:?}ms",
-        elapsed(start)
-    ));
-
-    let start = get_time();
-    let mut verifier_params_serialized = vec![];
-    nova_params
-        .1
-        .serialize_compressed(&mut verifier_params_serialized)
-        .unwrap();
-    dbg(format!(
-        "Nova prover params serialized: {:?}ms",
-        elapsed(start)
-    ));
-
-    dbg(format!(
-        "prover_params size: {} mb",
-        prover_params_serialized.len() / (1024 * 1024)
-    ));
-    dbg(format!(
-        "verifier_params size: {} mb",
-        verifier_params_serialized.len() / (1024 * 1024)
-    ));
-
-    vec![
-        b64.encode(&prover_params_serialized),
-        b64.encode(&prover_params_serialized),
-    ]
+/// Returns the key (hex) under which the canonical params of the given folding scheme and context
+/// can be cached (eg. in IndexedDB), known before generating them, see `cache::canonical_key`.
+#[wasm_bindgen]
+pub fn canonical_params_key(
+    scheme: Option<String>,
+    context: Option<String>,
+) -> Result<String, JsValue> {
+    let scheme = decode_scheme(scheme.as_deref())?;
+    let domain = decode_domain(context.as_deref());
+    Ok(cache::id_to_hex(&cache::canonical_key(scheme, domain)?))
 }
 
+/// Returns the id (hex) of the params of the given artefact (base64), see `backend::params_id`:
+/// for the params it is checked against their content, so that the params downloaded or read from
+/// a cache can be checked, and for the proofs it is the id of the params needed to verify them.
 #[wasm_bindgen]
-pub fn gen_sigs(n_steps: usize) -> Vec<String> {
-    let mut rng = OsRng;
+pub fn params_id(artefact: String) -> Result<String, JsValue> {
+    let b = b64.decode(artefact).map_err(EthDosError::from)?;
+    Ok(cache::id_to_hex(&backend::params_id(&b)?))
+}
+
+/// Derives the verifier params (base64) from the prover params (base64), see
+/// `api::verifier_params_from_prover_params`.
+#[cfg(feature = "prover")]
+#[wasm_bindgen]
+pub fn verifier_params_from_prover_params(prover_params: String) -> Result<String, JsValue> {
+    let pp = b64.decode(prover_params).map_err(EthDosError::from)?;
+    Ok(b64.encode(backend::verifier_params(&pp)?))
+}
+
+/// Generates a chain of `n_steps` signatures (base64) of random keys in the default domain. With a
+/// `seed`, the keys and signatures are reproducible.
+#[wasm_bindgen]
+pub fn gen_sigs(n_steps: usize, seed: Option<u64>) -> Result<Vec<String>, JsValue> {
     let poseidon_config = poseidon_canonical_config::<Fr>();
 
-    let sigs: Vec<SigPk<EdwardsProjective>> = gen_signatures(&mut rng, &poseidon_config, n_steps);
-    let b: Vec<Vec<u8>> = sigs.iter().map(|&s| s.to_bytes()).collect();
-    b.iter().map(|s| b64.encode(s)).collect::<Vec<String>>()
+    let domain = api::default_domain();
+
+    let sigs: Vec<SigPk<EdwardsProjective>> = match seed {
+        Some(seed) => gen_signatures(
+            &mut api::seeded_rng(seed),
+            &poseidon_config,
+            domain,
+            n_steps,
+        )?,
+        None => gen_signatures(&mut OsRng, &poseidon_config, domain, n_steps)?,
+    };
+    sigs.iter()
+        .map(|s| Ok(b64.encode(api::serialize_sig_pk(s, domain)?)))
+        .collect()
 }
 
+/// Folds the given signatures, checking that none of them is revoked in the given revocation tree
+/// (base64, as returned by `revoke_pk`), see `api::fold_sigs`. Without revocation tree, the empty
+/// one is used. With a `seed`, the randomness of the folding is reproducible. `on_progress` is
+/// called after each folding step with `(step, n_steps, elapsed_ms, eta_ms)`, and the folding is
+/// cancelled if it returns `false` (or throws), see `api::fold_sigs_with_progress`.
+#[cfg(feature = "prover")]
 #[wasm_bindgen]
-pub fn fold_sigs(params: Vec<String>, sigs_pks: Vec<String>) -> String {
+pub fn fold_sigs(
+    params: Vec<String>,
+    sigs_pks: Vec<String>,
+    revocations: Option<String>,
+    seed: Option<u64>,
+    on_progress: Option<js_sys::Function>,
+) -> Result<String, JsValue> {
     dbg("starting fold_sigs (rust)".to_string());
 
-    let poseidon_config = poseidon_canonical_config::<Fr>();
+    let (pp, vp) = decode_params(&params)?;
+    let domain = api::artefact_domain(&pp)?;
+    let pks_sigs = sigs_pks
+        .iter()
+        .map(|s| decode_sig_pk(s, domain))
+        .collect::<Result<Vec<_>, _>>()?;
+    let revocations = decode_revocations(revocations.as_deref())?;
+
+    let cancel = api::CancellationToken::new();
+    let progress = |p: api::FoldProgress| {
+        let on_progress = match &on_progress {
+            Some(on_progress) => on_progress,
+            None => return,
+        };
+        let args = js_sys::Array::of4(
+            &JsValue::from(p.step as u32),
+            &JsValue::from(p.n_steps as u32),
+            &JsValue::from(p.elapsed_ms as f64),
+            &JsValue::from(p.eta_ms as f64),
+        );
+        match on_progress.apply(&JsValue::NULL, &args) {
+            Ok(v) if v.as_bool() == Some(false) => cancel.cancel(),
+            Ok(_) => {}
+            Err(e) => {
+                dbg(format!(
+                    "on_progress error, cancelling the folding: {:?}",
+                    e
+                ));
+                cancel.cancel();
+            }
+        }
+    };
+    let ivc_proof = match seed {
+        Some(seed) => backend::fold_with_progress(
+            &pp,
+            &vp,
+            &pks_sigs,
+            &revocations,
+            &mut api::seeded_rng(seed),
+            progress,
+            &cancel,
+        )?,
+        None => backend::fold_with_progress(
+            &pp,
+            &vp,
+            &pks_sigs,
+            &revocations,
+            &mut OsRng,
+            progress,
+            &cancel,
+        )?,
+    };
+    Ok(b64.encode(ivc_proof))
+}
 
-    // parse sigs_pks
-    let b: Vec<Vec<u8>> = sigs_pks.iter().map(|s| b64.decode(s).unwrap()).collect();
-    let pks_sigs: Vec<SigPk<EdwardsProjective>> =
-        b.iter().map(|s| SigPk::from_bytes(s.clone())).collect();
-
-    // parse params
-    let start = get_time();
-    let pp = FS::pp_deserialize_with_mode(
-        &mut b64.decode(params[0].clone()).unwrap().as_slice(),
-        ark_serialize::Compress::Yes,
-        ark_serialize::Validate::Yes,
-        poseidon_config.clone(), // fcircuit_params
-    )
-    .unwrap();
-    let vp = FS::vp_deserialize_with_mode(
-        &mut b64.decode(params[1].clone()).unwrap().as_slice(),
-        ark_serialize::Compress::Yes,
-        ark_serialize::Validate::Yes,
-        poseidon_config.clone(), // fcircuit_params
-    )
-    .unwrap();
-    let fs_params = (pp, vp);
-    dbg(format!(
-        "params (prover & verifier) deserialization: {:?}ms",
-        elapsed(start)
-    ));
-
-    // set the initial state
-    let xy = pks_sigs[0].pk.0.xy().unwrap();
-    let pk0 = vec![xy.0, xy.1];
-    let z_0: Vec<Fr> = [pk0.clone(), pk0, vec![Fr::zero()]].concat();
-
-    type FC = EthDosCircuit<Fr, EdwardsProjective, EdwardsVar>;
-    let f_circuit = FC::new(poseidon_config.clone()).unwrap();
-
-    // initialize the folding scheme engine, in our case we use Nova
-    let mut nova = FS::init(&fs_params, f_circuit, z_0.clone()).unwrap();
-    let rng = OsRng;
-    let n_steps = sigs_pks.len();
-
-    let start_full = get_time();
-    #[allow(clippy::needless_range_loop)]
-    for i in 0..n_steps {
-        let start = get_time();
-        nova.prove_step(rng, pks_sigs[i], None).unwrap();
-        dbg(format!(
-            "Nova::prove_step {}: {:?}ms",
-            nova.i,
-            elapsed(start)
-        ));
-    }
-    dbg(format!(
-        "Nova's all {} steps time: {:?}ms",
-        n_steps,
-        elapsed(start_full)
-    ));
-
-    let ivc_proof = nova.ivc_proof();
-    let mut ivc_proof_bytes = vec![];
-    ivc_proof
-        .serialize_compressed(&mut ivc_proof_bytes)
-        .unwrap();
+/// Extends the given IVC proof by one more step with the new signature, see `api::extend_proof`.
+#[cfg(feature = "prover")]
+#[wasm_bindgen]
+pub fn extend_proof(
+    params: Vec<String>,
+    ivc_proof_b64: String,
+    sig_pk_b64: String,
+    revocations: Option<String>,
+) -> Result<String, JsValue> {
+    dbg("starting extend_proof (rust)".to_string());
+
+    let (pp, vp) = decode_params(&params)?;
+    let sig_pk = decode_sig_pk(&sig_pk_b64, api::artefact_domain(&pp)?)?;
+    let ivc_proof = b64.decode(ivc_proof_b64).map_err(EthDosError::from)?;
+    let revocations = decode_revocations(revocations.as_deref())?;
+
+    let ivc_proof = backend::extend(&pp, &vp, &ivc_proof, sig_pk, &revocations)?;
+    Ok(b64.encode(ivc_proof))
+}
 
-    let ivc_proof_bytes_comp = lz4_flex::block::compress_prepend_size(&ivc_proof_bytes);
+#[wasm_bindgen]
+pub fn verify_proof(verifier_params: String, ivc_proof_b64: String) -> Result<String, JsValue> {
+    let vp = b64.decode(verifier_params).map_err(EthDosError::from)?;
+    let proof = b64.decode(ivc_proof_b64).map_err(EthDosError::from)?;
 
-    dbg(format!(
-        "ivc_proof size (uncompressed): {} mb",
-        ivc_proof_bytes.len() / (1024 * 1024)
-    ));
-    dbg(format!(
-        "ivc_proof size (compressed): {} mb",
-        ivc_proof_bytes_comp.len() / (1024 * 1024)
-    ));
+    backend::verify(&vp, &proof)?;
+    Ok("verified".to_string())
+}
 
-    b64.encode(ivc_proof_bytes_comp)
+/// Same as `verify_proof`, but also checking the public statement of the proof (see
+/// `api::verify_proof_state`). The public keys are given in base64 of their bytes representation,
+/// the revocation root as a decimal string (by default, the root of the empty tree), and `now` in
+/// seconds since the unix epoch. Returns the decoded state as
+/// `[pk_0, pk_i, degree, not_after, weight, labels]`, where `not_after` is the time at which the
+/// first vouch of the chain expires, `weight` the sum of the weights of its vouches and `labels`
+/// the bitmask of their labels (see `api::check_attributes`).
+#[wasm_bindgen]
+pub fn verify_proof_state(
+    verifier_params: String,
+    ivc_proof_b64: String,
+    expected_pk_0: String,
+    revocation_root: Option<String>,
+    now: u32,
+    expected_pk_i: Option<String>,
+    max_degree: Option<u32>,
+) -> Result<Vec<String>, JsValue> {
+    let vp = b64.decode(verifier_params).map_err(EthDosError::from)?;
+    let proof = b64.decode(ivc_proof_b64).map_err(EthDosError::from)?;
+    let expected_pk_0 = decode_pk(&expected_pk_0)?;
+    let expected_pk_i = expected_pk_i.map(|pk| decode_pk(&pk)).transpose()?;
+
+    let state: ProofState = backend::verify_state(
+        &vp,
+        &proof,
+        expected_pk_0,
+        decode_revocation_root(revocation_root.as_deref())?,
+        u64::from(now),
+        expected_pk_i,
+        max_degree.map(u64::from),
+    )?;
+    Ok(vec![
+        b64.encode(state.pk_0.to_bytes()),
+        b64.encode(state.pk_i.to_bytes()),
+        state.degree.to_string(),
+        state.validity.not_after.to_string(),
+        state.weight.to_string(),
+        state.labels.to_string(),
+    ])
 }
 
+/// Proves the ownership of the last public key of the proof by signing the verifier's challenge
+/// (decimal string) with its secret key, see `api::prove_ownership`. Returns the ownership
+/// signature in base64.
 #[wasm_bindgen]
-pub fn verify_proof(verifier_params: String, ivc_proof_b64: String) -> String {
-    let poseidon_config = poseidon_canonical_config::<Fr>();
+pub fn prove_ownership(
+    ivc_proof_b64: String,
+    secret_key: String,
+    password: Option<String>,
+    challenge: String,
+) -> Result<String, JsValue> {
+    let ivc_proof = b64.decode(ivc_proof_b64).map_err(EthDosError::from)?;
+    let sk = keys::SecretKey::from_bytes(
+        &b64.decode(secret_key).map_err(EthDosError::from)?,
+        password.as_deref(),
+    )?;
+
+    let ownership = backend::prove_ownership(&ivc_proof, &sk, decode_fr(&challenge)?)?;
+    Ok(b64.encode(ownership))
+}
+
+/// Same as `verify_proof_state`, but also checking the ownership signature of the last public key
+/// for the given challenge (decimal string), see `api::verify_with_ownership`. Returns the decoded
+/// state as `[pk_0, pk_i, degree, not_after, weight, labels]`.
+#[wasm_bindgen]
+pub fn verify_with_ownership(
+    verifier_params: String,
+    ivc_proof_b64: String,
+    ownership_b64: String,
+    challenge: String,
+    expected_pk_0: String,
+    revocation_root: Option<String>,
+    now: u32,
+    max_degree: Option<u32>,
+) -> Result<Vec<String>, JsValue> {
+    let vp = b64.decode(verifier_params).map_err(EthDosError::from)?;
+    let proof = b64.decode(ivc_proof_b64).map_err(EthDosError::from)?;
+    let ownership = b64.decode(ownership_b64).map_err(EthDosError::from)?;
+
+    let state = backend::verify_with_ownership(
+        &vp,
+        &proof,
+        &ownership,
+        decode_fr(&challenge)?,
+        decode_pk(&expected_pk_0)?,
+        decode_revocation_root(revocation_root.as_deref())?,
+        u64::from(now),
+        max_degree.map(u64::from),
+    )?;
+    Ok(vec![
+        b64.encode(state.pk_0.to_bytes()),
+        b64.encode(state.pk_i.to_bytes()),
+        state.degree.to_string(),
+        state.validity.not_after.to_string(),
+        state.weight.to_string(),
+        state.labels.to_string(),
+    ])
+}
 
-    let vp = FS::vp_deserialize_with_mode(
-        &mut b64.decode(verifier_params.clone()).unwrap().as_slice(),
-        ark_serialize::Compress::Yes,
-        ark_serialize::Validate::Yes,
-        poseidon_config.clone(), // fcircuit_params
-    )
-    .unwrap();
-    let ivc_proof_bytes_comp = b64.decode(ivc_proof_b64).unwrap();
-    let ivc_proof_bytes =
-        lz4_flex::block::decompress_size_prepended(&ivc_proof_bytes_comp).unwrap();
-    let proof = <Nova<G1, G2, FC, Pedersen<G1>, Pedersen<G2>, false> as FoldingScheme<
-        G1,
-        G2,
-        FC,
-    >>::IVCProof::deserialize_compressed(ivc_proof_bytes.as_slice())
-    .unwrap();
-
-    FS::verify(
-        vp, // Nova's verifier params
-        proof,
-    )
-    .unwrap();
-    "verified".to_string()
+/// Returns `[decider_params, decider_verifier_params]`, in base64, for the domain of the given
+/// context (by default "ethdos"), see `decider::gen_decider_params`. With a `seed`, the params are
+/// generated from it, only for tests (see `api::seeded_rng`).
+#[cfg(feature = "decider")]
+#[wasm_bindgen]
+pub fn gen_decider_params(
+    context: Option<String>,
+    seed: Option<u64>,
+) -> Result<Vec<String>, JsValue> {
+    let domain = decode_domain(context.as_deref());
+    let (params, vp) = match seed {
+        Some(seed) => backend::setup_decider_with_rng(domain, &mut api::seeded_rng(seed))?,
+        None => backend::setup_decider(domain)?,
+    };
+    Ok(vec![b64.encode(params), b64.encode(vp)])
+}
+
+/// Folds the given signatures followed by the presentation step for the given challenge (decimal
+/// string) and maximum degree, signed with the secret key of the last public key of the chain, and
+/// compresses it with the Decider params (base64), see `decider::present`. Returns the compressed
+/// presentation proof in base64.
+#[cfg(feature = "decider")]
+#[wasm_bindgen]
+pub fn present(
+    decider_params: String,
+    sigs_pks: Vec<String>,
+    revocations: Option<String>,
+    secret_key: String,
+    password: Option<String>,
+    challenge: String,
+    max_degree: u32,
+) -> Result<String, JsValue> {
+    let decider_params = b64.decode(decider_params).map_err(EthDosError::from)?;
+    let domain = api::artefact_domain(&decider_params)?;
+    let pks_sigs = sigs_pks
+        .iter()
+        .map(|s| decode_sig_pk(s, domain))
+        .collect::<Result<Vec<_>, _>>()?;
+    let revocations = decode_revocations(revocations.as_deref())?;
+    let sk = keys::SecretKey::from_bytes(
+        &b64.decode(secret_key).map_err(EthDosError::from)?,
+        password.as_deref(),
+    )?;
+
+    let proof = backend::present(
+        &decider_params,
+        &pks_sigs,
+        &revocations,
+        &sk,
+        decode_fr(&challenge)?,
+        u64::from(max_degree),
+    )?;
+    Ok(b64.encode(proof))
+}
+
+/// Verifies the compressed presentation proof for the given challenge (decimal string) and maximum
+/// degree, at the time `now` (in seconds since the unix epoch), see `decider::verify_presentation`.
+/// Returns `[pk_0, within_max_degree, nullifier]`, where `within_max_degree` is "true" if the
+/// degree of the proof is at most `max_degree`.
+#[cfg(feature = "decider")]
+#[wasm_bindgen]
+pub fn verify_presentation(
+    decider_verifier_params: String,
+    proof_b64: String,
+    expected_pk_0: String,
+    revocation_root: Option<String>,
+    now: u32,
+    challenge: String,
+    max_degree: u32,
+) -> Result<Vec<String>, JsValue> {
+    let vp = b64
+        .decode(decider_verifier_params)
+        .map_err(EthDosError::from)?;
+    let proof = b64.decode(proof_b64).map_err(EthDosError::from)?;
+
+    let presentation = backend::verify_presentation(
+        &vp,
+        &proof,
+        decode_pk(&expected_pk_0)?,
+        decode_revocation_root(revocation_root.as_deref())?,
+        u64::from(now),
+        decode_fr(&challenge)?,
+        u64::from(max_degree),
+    )?;
+    Ok(vec![
+        b64.encode(presentation.pk_0.to_bytes()),
+        presentation.within_max_degree.to_string(),
+        presentation.nullifier.to_string(),
+    ])
+}
+
+/// Generates a new secret key, returns `[secret_key, public_key]` in base64. If a password is
+/// given, the secret key is encrypted with it.
+#[wasm_bindgen]
+pub fn keygen(password: Option<String>) -> Result<Vec<String>, JsValue> {
+    let mut rng = OsRng;
+    let (sk, pk) = keys::keygen(&mut rng)?;
+    let sk_bytes = match password {
+        Some(password) => sk.to_encrypted_bytes(&mut rng, &password)?,
+        None => sk.to_bytes(),
+    };
+    Ok(vec![b64.encode(sk_bytes), b64.encode(pk.to_bytes())])
+}
+
+/// Signs the given public key (base64) with the secret key (base64, as returned by `keygen`),
+/// returns the signature-and-public-key (base64) to be used in the folding. If `not_before` or
+/// `not_after` (in seconds since the unix epoch) are given, the vouch is only valid within that
+/// window. If `label` or `weight` are given, they are signed as the attributes of the vouch (see
+/// `signature::Attributes`). The vouch is only valid for the params of the same context (by
+/// default "ethdos").
+#[wasm_bindgen]
+pub fn sign_pk(
+    secret_key: String,
+    password: Option<String>,
+    prev_pk: String,
+    not_before: Option<u32>,
+    not_after: Option<u32>,
+    label: Option<u8>,
+    weight: Option<u32>,
+    context: Option<String>,
+) -> Result<String, JsValue> {
+    let domain = decode_domain(context.as_deref());
+    let sk = keys::SecretKey::from_bytes(
+        &b64.decode(secret_key).map_err(EthDosError::from)?,
+        password.as_deref(),
+    )?;
+    let prev_pk = decode_pk(&prev_pk)?;
+    let validity = if not_before.is_some() || not_after.is_some() {
+        Some(Validity::new(
+            not_before.map_or(0, u64::from),
+            not_after.map_or(NO_EXPIRY, u64::from),
+        )?)
+    } else {
+        None
+    };
+    let attributes = if label.is_some() || weight.is_some() {
+        Some(Attributes::new(
+            label.unwrap_or(NO_LABEL),
+            weight.unwrap_or(DEFAULT_WEIGHT),
+        )?)
+    } else {
+        None
+    };
+    let sig_pk = keys::sign_vouch(&sk, domain, prev_pk, validity, attributes)?;
+    Ok(b64.encode(api::serialize_sig_pk(&sig_pk, domain)?))
+}
+
+/// Returns the public key (base64) of the given signature-and-public-key (base64), as returned by
+/// `gen_sigs`.
+#[wasm_bindgen]
+pub fn sig_pk_public_key(sig_pk_b64: String) -> Result<String, JsValue> {
+    let b = b64.decode(sig_pk_b64).map_err(EthDosError::from)?;
+    let sig_pk = api::deserialize_sig_pk(&b, api::artefact_domain(&b)?)?;
+    Ok(b64.encode(sig_pk.pk.to_bytes()))
+}
+
+/// Revokes the public key (base64) in the given revocation tree (base64, a new one if not given),
+/// invalidating all its vouches. Returns the updated revocation tree in base64.
+#[wasm_bindgen]
+pub fn revoke_pk(revocations: Option<String>, public_key: String) -> Result<String, JsValue> {
+    let mut revocations = decode_revocations(revocations.as_deref())?;
+    revocations.revoke_pk(decode_pk(&public_key)?)?;
+    Ok(b64.encode(api::serialize_revocations(&revocations)?))
+}
+
+/// Revokes the vouch of `public_key` for `prev_public_key` (both in base64) in the given revocation
+/// tree (base64, a new one if not given). Returns the updated revocation tree in base64.
+#[wasm_bindgen]
+pub fn revoke_vouch(
+    revocations: Option<String>,
+    prev_public_key: String,
+    public_key: String,
+) -> Result<String, JsValue> {
+    let mut revocations = decode_revocations(revocations.as_deref())?;
+    revocations.revoke_vouch(decode_pk(&prev_public_key)?, decode_pk(&public_key)?)?;
+    Ok(b64.encode(api::serialize_revocations(&revocations)?))
+}
+
+/// Returns the root (decimal string) of the given revocation tree (base64, by default the empty
+/// one), which is the value that the verifiers need.
+#[wasm_bindgen]
+pub fn revocation_root(revocations: Option<String>) -> Result<String, JsValue> {
+    Ok(decode_revocations(revocations.as_deref())?
+        .root()
+        .to_string())
+}
+
+// decodes the base64 of the prover & verifier params, as returned by `gen_params`
+fn decode_params(params: &[String]) -> Result<(Vec<u8>, Vec<u8>), EthDosError> {
+    if params.len() != 2 {
+        return Err(EthDosError::Deserialization(format!(
+            "expected prover & verifier params, got {} values",
+            params.len()
+        )));
+    }
+    Ok((b64.decode(&params[0])?, b64.decode(&params[1])?))
+}
+
+fn decode_sig_pk(sig_pk_b64: &str, domain: Fr) -> Result<SigPk<EdwardsProjective>, EthDosError> {
+    api::deserialize_sig_pk(&b64.decode(sig_pk_b64)?, domain)
+}
+
+// parses the folding scheme, by default Nova
+fn decode_scheme(scheme: Option<&str>) -> Result<Scheme, EthDosError> {
+    match scheme {
+        Some(scheme) => scheme.parse::<Scheme>(),
+        None => Ok(Scheme::Nova),
+    }
+}
+
+// returns the domain of the given context, by default the one of "ethdos"
+fn decode_domain(context: Option<&str>) -> Fr {
+    match context {
+        Some(context) => domain_tag(context),
+        None => api::default_domain(),
+    }
+}
+
+fn decode_pk(pk_b64: &str) -> Result<PublicKey<EdwardsProjective>, EthDosError> {
+    pk_from_bytes(&b64.decode(pk_b64)?)
+}
+
+fn decode_fr(v: &str) -> Result<Fr, EthDosError> {
+    v.parse::<Fr>()
+        .map_err(|_| EthDosError::Deserialization(format!("invalid field element '{}'", v)))
+}
+
+// decodes the base64 of the revocation tree, returning the empty one if not given
+fn decode_revocations(revocations_b64: Option<&str>) -> Result<RevocationTree, EthDosError> {
+    match revocations_b64 {
+        Some(b) => api::deserialize_revocations(&b64.decode(b)?),
+        None => Ok(RevocationTree::new(&poseidon_canonical_config::<Fr>())),
+    }
+}
+
+fn decode_revocation_root(root: Option<&str>) -> Result<Fr, EthDosError> {
+    match root {
+        Some(root) => decode_fr(root),
+        None => Ok(RevocationTree::empty_root(
+            &poseidon_canonical_config::<Fr>(),
+        )),
+    }
 }
 
 #[cfg(test)]
@@ -235,8 +603,10 @@ mod tests {
     };
 
     use crate::{
-        fcircuit::EthDosCircuit,
-        signature::gen_signatures,
+        api,
+        fcircuit::{EthDosCircuit, EthDosStep},
+        revocation::RevocationTree,
+        signature::{gen_signatures, NO_EXPIRY},
         utils::{dbg, elapsed, get_time},
     };
 
@@ -253,16 +623,27 @@ mod tests {
         let mut rng = OsRng;
         let poseidon_config = poseidon_canonical_config::<Fr>();
 
+        let domain = api::default_domain();
+
         let pks_sigs =
-            gen_signatures::<OsRng, EdwardsProjective>(&mut rng, &poseidon_config, N_STEPS);
+            gen_signatures::<OsRng, EdwardsProjective>(&mut rng, &poseidon_config, domain, N_STEPS)
+                .unwrap();
 
-        // set the initial state
+        // set the initial state, with an empty revocation tree
+        let revocations = RevocationTree::new(&poseidon_config);
         let xy = pks_sigs[0].pk.0.xy().unwrap();
         let pk0 = vec![xy.0, xy.1];
-        let z_0: Vec<Fr> = [pk0.clone(), pk0, vec![Fr::zero()]].concat();
+        let z_0: Vec<Fr> = [
+            pk0.clone(),
+            pk0,
+            vec![Fr::zero(); 5],
+            vec![revocations.root(), Fr::zero(), Fr::from(NO_EXPIRY)],
+            vec![Fr::zero(); 2],
+        ]
+        .concat();
 
         type FC = EthDosCircuit<Fr, EdwardsProjective, EdwardsVar>;
-        let f_circuit = FC::new(poseidon_config.clone()).unwrap();
+        let f_circuit = FC::new(api::circuit_params(domain)).unwrap();
 
         // define type aliases for the FoldingScheme (FS) and Decider (D), to avoid writing the
         // whole type each time
@@ -280,10 +661,12 @@ mod tests {
 
         // run n steps of the folding iteration
         let start_full = get_time();
-        #[allow(clippy::needless_range_loop)]
         for i in 0..N_STEPS {
             let start = get_time();
-            nova.prove_step(rng, pks_sigs[i], None).unwrap();
+            let prev_pk = pks_sigs[i.saturating_sub(1)].pk;
+            let non_revocation = revocations.non_revocation(prev_pk, pks_sigs[i].pk).unwrap();
+            nova.prove_step(rng, EthDosStep::vouch(pks_sigs[i], non_revocation), None)
+                .unwrap();
             dbg(format!("Nova::prove_step {}: {:?}", nova.i, elapsed(start)));
         }
         dbg(format!(
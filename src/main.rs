@@ -0,0 +1,452 @@
+//! Native command line tool covering the full flow: setup, key generation, signing, folding,
+//! extending, verifying and inspecting the IVC proofs. All the artefacts are read from and written
+//! to files.
+use ark_bn254::Fr;
+use base64::{engine::general_purpose::STANDARD as b64, Engine as _};
+use rand::rngs::OsRng;
+use std::error::Error;
+use std::fs;
+
+use arkeddsa::{ed_on_bn254_twist::EdwardsProjective, PublicKey};
+use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+
+use ethdos_fold::cache::{self, ParamsCache};
+use ethdos_fold::format::{self, Scheme};
+use ethdos_fold::keys::{self, SecretKey};
+use ethdos_fold::revocation::RevocationTree;
+use ethdos_fold::signature::{
+    domain_tag, pk_from_bytes, Attributes, Validity, DEFAULT_CONTEXT, NO_EXPIRY,
+};
+use ethdos_fold::{api, backend};
+
+// environment variable from which the password to encrypt/decrypt the secret keys is read
+const PASSWORD_ENV: &str = "ETHDOS_PASSWORD";
+// environment variable with the path of the revocation tree used to fold and verify the proofs
+const REVOCATIONS_ENV: &str = "ETHDOS_REVOCATIONS";
+// environment variable with the context of the domain of the params and signatures
+const CONTEXT_ENV: &str = "ETHDOS_CONTEXT";
+// environment variable with the directory of the cache of the canonical params
+const PARAMS_CACHE_ENV: &str = "ETHDOS_PARAMS_CACHE";
+
+const USAGE: &str = "usage: ethdos-fold <command> [args]
+
+commands:
+  setup <prover_params_out> <verifier_params_out> [--scheme <nova|hypernova|protogalaxy>]
+        [--canonical]
+  verifier-params <prover_params> <verifier_params_out>
+  params-id <params_or_proof>
+  keygen <secret_key_out> <public_key_out>
+  sign <secret_key> <prev_public_key> <sig_out> [--not-before <time>] [--not-after <time>]
+       [--label <n>] [--weight <n>]
+  fold <prover_params> <verifier_params> <proof_out> <sig_0> [<sig_1> ...]
+  fold-batch <prover_params> <verifier_params> <chains> <proofs_out_dir> [--threads <n>]
+  extend <prover_params> <verifier_params> <proof> <sig> <proof_out>
+  verify <verifier_params> <proof> [--pk0 <public_key>] [--pki <public_key>] [--max-degree <n>]
+         [--now <time>] [--max-weight <n>] [--labels <n>,<n>,...]
+  prove-ownership <proof> <secret_key> <challenge> <ownership_out>
+  verify-ownership <verifier_params> <proof> <ownership> <pk0> <challenge> [--max-degree <n>]
+  decider-setup <decider_params_out> <decider_verifier_params_out>
+  present <decider_params> <secret_key> <challenge> <max_degree> <proof_out> <sig_0> [<sig_1> ...]
+  verify-presentation <decider_verifier_params> <proof> <pk0> <challenge> <max_degree>
+  inspect <proof>
+  revoke <revocations> pk <public_key>
+  revoke <revocations> vouch <prev_public_key> <public_key>
+
+The folding scheme is chosen at setup (by default nova), the rest of the commands use the scheme
+of the given params.
+With --canonical, the params are derived from a public seed that only depends on the circuit
+config, so that every setup with the same scheme and context generates the same params. If the
+ETHDOS_PARAMS_CACHE environment variable is set, the canonical params are read from (or added to)
+the cache at that directory instead of being generated each time. params-id prints the id of the
+params, which is also included in the proofs generated with them.
+decider-setup, present and verify-presentation (with the `decider` feature) generate the Decider
+params, fold the chain followed by the presentation step and compress it into a Decider proof,
+which does not contain the witnesses of the folding, and verify it.
+If the ETHDOS_PASSWORD environment variable is set, the secret keys are encrypted with it.
+If the ETHDOS_REVOCATIONS environment variable is set, it is the path of the revocation tree used
+to fold, extend and verify the proofs, otherwise the empty tree is used.
+The times are in seconds since the unix epoch. A signature with --not-before or --not-after is a
+vouch that is only valid within that window, the proofs are verified at the current time (or at
+--now) and rejected if any of their vouches is not valid at that time.
+A signature with --label (0 to 63) or --weight is a vouch with those attributes, the proofs are
+rejected if the sum of the weights of their vouches is greater than --max-weight, or if any of
+their vouches has a label not in --labels (the vouches without attributes have the label 0).
+fold-batch (with the `parallel` feature) folds concurrently the chains of the <chains> file, one
+chain per line with the paths of its signatures separated by spaces, into <proofs_out_dir>/<n>.bin
+for the chain of the line n (starting at 0), by default with one thread per CPU.
+The ETHDOS_CONTEXT environment variable (by default \"ethdos\") sets the domain of the params
+generated at setup and of the signatures, so that different communities run isolated graphs. The
+rest of the commands use the domain of the given params.";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (cmd, args) = args.split_first().ok_or(USAGE)?;
+    match (cmd.as_str(), args) {
+        ("setup", [pp_out, vp_out, opts @ ..]) => {
+            let mut scheme = Scheme::Nova;
+            let mut canonical = false;
+            let mut opts = opts.iter();
+            while let Some(opt) = opts.next() {
+                match opt.as_str() {
+                    "--scheme" => scheme = opts.next().ok_or(USAGE)?.parse::<Scheme>()?,
+                    "--canonical" => canonical = true,
+                    _ => return Err(USAGE.into()),
+                }
+            }
+            let domain = context_domain();
+            let (pp, vp) = match (canonical, std::env::var(PARAMS_CACHE_ENV)) {
+                (true, Ok(dir)) => ParamsCache::new(dir)?.canonical(scheme, domain)?,
+                (true, Err(_)) => backend::setup_canonical(scheme, domain)?,
+                (false, _) => backend::setup(scheme, domain)?,
+            };
+            fs::write(pp_out, pp)?;
+            fs::write(vp_out, vp)?;
+            println!(
+                "params id: {}",
+                cache::id_to_hex(&format::read_header(&vp)?.params_id)
+            );
+        }
+        ("verifier-params", [pp, vp_out]) => {
+            fs::write(vp_out, backend::verifier_params(&fs::read(pp)?)?)?;
+        }
+        ("params-id", [artefact]) => {
+            println!(
+                "{}",
+                cache::id_to_hex(&backend::params_id(&fs::read(artefact)?)?)
+            );
+        }
+        ("keygen", [sk_out, pk_out]) => {
+            let (sk, pk) = keys::keygen(&mut OsRng)?;
+            let sk_bytes = match std::env::var(PASSWORD_ENV) {
+                Ok(password) => sk.to_encrypted_bytes(&mut OsRng, &password)?,
+                Err(_) => sk.to_bytes(),
+            };
+            fs::write(sk_out, sk_bytes)?;
+            fs::write(pk_out, pk.to_bytes())?;
+            println!("public key: {}", b64.encode(pk.to_bytes()));
+        }
+        ("sign", [sk, prev_pk, sig_out, opts @ ..]) => {
+            let password = std::env::var(PASSWORD_ENV).ok();
+            let sk = SecretKey::from_bytes(&fs::read(sk)?, password.as_deref())?;
+
+            let mut validity = None;
+            let mut attributes = None;
+            let mut opts = opts.iter();
+            while let Some(opt) = opts.next() {
+                let value = opts.next().ok_or(USAGE)?;
+                match opt.as_str() {
+                    "--not-before" => {
+                        validity.get_or_insert_with(Validity::default).not_before = value.parse()?
+                    }
+                    "--not-after" => {
+                        validity.get_or_insert_with(Validity::default).not_after = value.parse()?
+                    }
+                    "--label" => {
+                        attributes.get_or_insert_with(Attributes::default).label = value.parse()?
+                    }
+                    "--weight" => {
+                        attributes.get_or_insert_with(Attributes::default).weight = value.parse()?
+                    }
+                    _ => return Err(USAGE.into()),
+                }
+            }
+            let validity = validity
+                .map(|v| Validity::new(v.not_before, v.not_after))
+                .transpose()?;
+            let attributes = attributes
+                .map(|a| Attributes::new(a.label, a.weight))
+                .transpose()?;
+
+            let domain = context_domain();
+            let sig_pk = keys::sign_vouch(&sk, domain, read_pk(prev_pk)?, validity, attributes)?;
+            fs::write(sig_out, api::serialize_sig_pk(&sig_pk, domain)?)?;
+        }
+        ("fold", [pp, vp, proof_out, sigs @ ..]) if !sigs.is_empty() => {
+            let pp = fs::read(pp)?;
+            let domain = api::artefact_domain(&pp)?;
+            let pks_sigs = sigs
+                .iter()
+                .map(|s| Ok(api::deserialize_sig_pk(&fs::read(s)?, domain)?))
+                .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+            let proof = backend::fold_with_progress(
+                &pp,
+                &fs::read(vp)?,
+                &pks_sigs,
+                &read_revocations()?,
+                &mut OsRng,
+                |p| {
+                    eprintln!(
+                        "step {}/{}: {}s elapsed, {}s remaining",
+                        p.step,
+                        p.n_steps,
+                        p.elapsed_ms / 1000,
+                        p.eta_ms / 1000
+                    )
+                },
+                &api::CancellationToken::new(),
+            )?;
+            fs::write(proof_out, proof)?;
+        }
+        #[cfg(feature = "parallel")]
+        ("fold-batch", [pp, vp, chains, out_dir, opts @ ..]) => {
+            let n_threads = match opts {
+                [] => None,
+                [opt, n] if opt == "--threads" => Some(n.parse::<usize>()?),
+                _ => return Err(USAGE.into()),
+            };
+            let pp = fs::read(pp)?;
+            let domain = api::artefact_domain(&pp)?;
+            let lines = fs::read_to_string(chains)?;
+            let chains = lines
+                .lines()
+                .map(|line| {
+                    line.split_whitespace()
+                        .map(|s| Ok(api::deserialize_sig_pk(&fs::read(s)?, domain)?))
+                        .collect::<Result<Vec<_>, Box<dyn Error>>>()
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let proofs = backend::fold_batch(
+                &pp,
+                &fs::read(vp)?,
+                &chains,
+                &read_revocations()?,
+                n_threads,
+                |p| eprintln!("folded {}/{} ({} failed)", p.done, p.total, p.failed),
+            )?;
+            fs::create_dir_all(out_dir)?;
+            let mut failed = 0;
+            for (i, proof) in proofs.into_iter().enumerate() {
+                match proof {
+                    Ok(proof) => fs::write(
+                        std::path::Path::new(out_dir).join(format!("{}.bin", i)),
+                        proof,
+                    )?,
+                    Err(e) => {
+                        eprintln!("chain {}: {}", i, e);
+                        failed += 1;
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(format!("{} of {} chains failed", failed, chains.len()).into());
+            }
+        }
+        ("extend", [pp, vp, proof, sig, proof_out]) => {
+            let pp = fs::read(pp)?;
+            let sig_pk = api::deserialize_sig_pk(&fs::read(sig)?, api::artefact_domain(&pp)?)?;
+            let proof = backend::extend(
+                &pp,
+                &fs::read(vp)?,
+                &fs::read(proof)?,
+                sig_pk,
+                &read_revocations()?,
+            )?;
+            fs::write(proof_out, proof)?;
+        }
+        ("verify", [vp, proof, opts @ ..]) => {
+            let vp = fs::read(vp)?;
+            let proof = fs::read(proof)?;
+
+            let mut pk_0 = None;
+            let mut pk_i = None;
+            let mut max_degree = None;
+            let mut now = None;
+            let mut max_weight = None;
+            let mut labels = None;
+            let mut opts = opts.iter();
+            while let Some(opt) = opts.next() {
+                let value = opts.next().ok_or(USAGE)?;
+                match opt.as_str() {
+                    "--pk0" => pk_0 = Some(read_pk(value)?),
+                    "--pki" => pk_i = Some(read_pk(value)?),
+                    "--max-degree" => max_degree = Some(value.parse::<u64>()?),
+                    "--now" => now = Some(value.parse::<u64>()?),
+                    "--max-weight" => max_weight = Some(value.parse::<u64>()?),
+                    "--labels" => labels = Some(parse_labels(value)?),
+                    _ => return Err(USAGE.into()),
+                }
+            }
+
+            let state = match pk_0 {
+                Some(pk_0) => {
+                    let root = read_revocations()?.root();
+                    let now = match now {
+                        Some(now) => now,
+                        None => unix_time()?,
+                    };
+                    backend::verify_state(&vp, &proof, pk_0, root, now, pk_i, max_degree)?
+                }
+                None => {
+                    if pk_i.is_some() || max_degree.is_some() || now.is_some() {
+                        return Err("--pki, --max-degree and --now require --pk0".into());
+                    }
+                    backend::verify(&vp, &proof)?
+                }
+            };
+            api::check_attributes(&state, max_weight, labels.as_deref())?;
+            print_state(&state);
+            println!("verified");
+        }
+        ("prove-ownership", [proof, sk, challenge, ownership_out]) => {
+            let password = std::env::var(PASSWORD_ENV).ok();
+            let sk = SecretKey::from_bytes(&fs::read(sk)?, password.as_deref())?;
+            let ownership = backend::prove_ownership(&fs::read(proof)?, &sk, parse_fr(challenge)?)?;
+            fs::write(ownership_out, ownership)?;
+        }
+        ("verify-ownership", [vp, proof, ownership, pk_0, challenge, opts @ ..]) => {
+            let max_degree = match opts {
+                [] => None,
+                [opt, n] if opt == "--max-degree" => Some(n.parse::<u64>()?),
+                _ => return Err(USAGE.into()),
+            };
+            let state = backend::verify_with_ownership(
+                &fs::read(vp)?,
+                &fs::read(proof)?,
+                &fs::read(ownership)?,
+                parse_fr(challenge)?,
+                read_pk(pk_0)?,
+                read_revocations()?.root(),
+                unix_time()?,
+                max_degree,
+            )?;
+            print_state(&state);
+            println!("verified");
+        }
+        #[cfg(feature = "decider")]
+        ("decider-setup", [params_out, vp_out]) => {
+            let (params, vp) = backend::setup_decider(context_domain())?;
+            fs::write(params_out, params)?;
+            fs::write(vp_out, vp)?;
+            println!(
+                "params id: {}",
+                cache::id_to_hex(&format::read_header(&vp)?.params_id)
+            );
+        }
+        #[cfg(feature = "decider")]
+        ("present", [params, sk, challenge, max_degree, proof_out, sigs @ ..])
+            if !sigs.is_empty() =>
+        {
+            let params = fs::read(params)?;
+            let domain = api::artefact_domain(&params)?;
+            let pks_sigs = sigs
+                .iter()
+                .map(|s| Ok(api::deserialize_sig_pk(&fs::read(s)?, domain)?))
+                .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+            let password = std::env::var(PASSWORD_ENV).ok();
+            let sk = SecretKey::from_bytes(&fs::read(sk)?, password.as_deref())?;
+            let proof = backend::present(
+                &params,
+                &pks_sigs,
+                &read_revocations()?,
+                &sk,
+                parse_fr(challenge)?,
+                max_degree.parse::<u64>()?,
+            )?;
+            fs::write(proof_out, proof)?;
+        }
+        #[cfg(feature = "decider")]
+        ("verify-presentation", [vp, proof, pk_0, challenge, max_degree]) => {
+            let presentation = backend::verify_presentation(
+                &fs::read(vp)?,
+                &fs::read(proof)?,
+                read_pk(pk_0)?,
+                read_revocations()?.root(),
+                unix_time()?,
+                parse_fr(challenge)?,
+                max_degree.parse::<u64>()?,
+            )?;
+            println!("pk_0: {}", b64.encode(presentation.pk_0.to_bytes()));
+            println!(
+                "degree <= {}: {}",
+                presentation.max_degree, presentation.within_max_degree
+            );
+            println!("nullifier: {}", presentation.nullifier);
+            println!("verified");
+        }
+        ("inspect", [proof]) => {
+            print_state(&backend::inspect(&fs::read(proof)?)?);
+        }
+        ("revoke", [revocations_file, kind, pks @ ..]) => {
+            // the revocation tree is created if it does not exist
+            let mut revocations = if std::path::Path::new(revocations_file).exists() {
+                api::deserialize_revocations(&fs::read(revocations_file)?)?
+            } else {
+                RevocationTree::new(&poseidon_canonical_config::<Fr>())
+            };
+            match (kind.as_str(), pks) {
+                ("pk", [pk]) => revocations.revoke_pk(read_pk(pk)?)?,
+                ("vouch", [prev_pk, pk]) => {
+                    revocations.revoke_vouch(read_pk(prev_pk)?, read_pk(pk)?)?
+                }
+                _ => return Err(USAGE.into()),
+            }
+            fs::write(revocations_file, api::serialize_revocations(&revocations)?)?;
+            println!("revocation root: {}", revocations.root());
+        }
+        _ => return Err(USAGE.into()),
+    }
+    Ok(())
+}
+
+// returns the domain of the context set at CONTEXT_ENV, by default the one of "ethdos"
+fn context_domain() -> Fr {
+    domain_tag(&std::env::var(CONTEXT_ENV).unwrap_or_else(|_| DEFAULT_CONTEXT.to_string()))
+}
+
+fn read_pk(path: &str) -> Result<PublicKey<EdwardsProjective>, Box<dyn Error>> {
+    Ok(pk_from_bytes(&fs::read(path)?)?)
+}
+
+fn read_revocations() -> Result<RevocationTree, Box<dyn Error>> {
+    match std::env::var(REVOCATIONS_ENV) {
+        Ok(path) => Ok(api::deserialize_revocations(&fs::read(path)?)?),
+        Err(_) => Ok(RevocationTree::new(&poseidon_canonical_config::<Fr>())),
+    }
+}
+
+// current time, in seconds since the unix epoch
+fn unix_time() -> Result<u64, Box<dyn Error>> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs())
+}
+
+fn parse_fr(v: &str) -> Result<Fr, Box<dyn Error>> {
+    v.parse::<Fr>()
+        .map_err(|_| format!("invalid field element '{}'", v).into())
+}
+
+fn parse_labels(v: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    v.split(',')
+        .map(|l| {
+            l.trim()
+                .parse::<u8>()
+                .map_err(|_| format!("invalid label '{}'", l).into())
+        })
+        .collect()
+}
+
+fn print_state(state: &api::ProofState) {
+    println!("pk_0: {}", b64.encode(state.pk_0.to_bytes()));
+    println!("pk_i: {}", b64.encode(state.pk_i.to_bytes()));
+    println!("degree: {}", state.degree);
+    println!("revocation root: {}", state.revocation_root);
+    if state.validity.not_after == NO_EXPIRY {
+        println!("valid from: {}, no expiry", state.validity.not_before);
+    } else {
+        println!(
+            "valid from: {}, until: {}",
+            state.validity.not_before, state.validity.not_after
+        );
+    }
+    println!("weight: {}", state.weight);
+    println!("labels: {:#x}", state.labels);
+}
@@ -0,0 +1,598 @@
+//! This file contains the revocation of public keys and of vouches (signatures of a public key over
+//! another one). The revoked values are the keys of an indexed Poseidon Merkle tree, and each
+//! folding step proves the non-membership of its signer public key and of its vouch in the tree,
+//! whose root is carried in the IVC state.
+//!
+//! Each leaf is `Poseidon(key, next_key)`, where `next_key` is the next larger revoked key (zero
+//! for the largest one), so the leaves form a sorted linked list, starting at the leaf of the zero
+//! key. Non-membership of a key is proven by opening its low leaf, the one of the largest revoked
+//! key smaller than it, and checking that the next key of the low leaf is larger than the key.
+//! The leaves are placed in order of revocation, so the depth of the tree does not depend on the
+//! bit size of the keys.
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::{
+    constraints::CryptographicSpongeVar,
+    poseidon::{constraints::PoseidonSpongeVar, PoseidonConfig, PoseidonSponge},
+    Absorb, CryptographicSponge,
+};
+use ark_ff::PrimeField;
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    convert::ToBitsGadget,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+};
+use ark_relations::r1cs::{ConstraintSystemRef, Namespace, SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::Zero;
+use core::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+
+use arkeddsa::ed_on_bn254_twist::EdwardsProjective;
+use arkeddsa::PublicKey;
+
+use crate::error::EthDosError;
+
+/// Depth of the revocation tree, which holds up to `2^REVOCATION_DEPTH - 1` revoked values (the
+/// first leaf is the one of the zero key).
+pub const REVOCATION_DEPTH: usize = 32;
+
+/// Domain separator of the revocation key of a vouch, so that it can not collide with the one of a
+/// public key.
+pub const REVOKED_VOUCH_TAG: u64 = 3;
+
+fn hash_two<F: PrimeField + Absorb>(poseidon_config: &PoseidonConfig<F>, l: F, r: F) -> F {
+    let mut poseidon = PoseidonSponge::new(poseidon_config);
+    poseidon.absorb(&vec![l, r]);
+    poseidon.squeeze_field_elements::<F>(1)[0]
+}
+
+fn hash_two_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    poseidon_config: &PoseidonConfig<F>,
+    l: FpVar<F>,
+    r: FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut poseidon = PoseidonSpongeVar::new(cs, poseidon_config);
+    poseidon.absorb(&vec![l, r])?;
+    let h = poseidon.squeeze_field_elements(1)?;
+    h.first().cloned().ok_or(SynthesisError::Unsatisfiable)
+}
+
+/// Revocation key of a public key, which is `Poseidon(pk_x, pk_y)`. Unlike the signed messages, it
+/// does not depend on the domain, so a revocation tree can be shared by several domains.
+pub fn pk_key(poseidon_config: &PoseidonConfig<Fr>, pk: PublicKey<EdwardsProjective>) -> Fr {
+    hash_two(poseidon_config, pk.0.x, pk.0.y)
+}
+
+/// Revocation key of the vouch of `pk` for `prev_pk`, ie. the edge `prev_pk <- pk`
+pub fn vouch_key(
+    poseidon_config: &PoseidonConfig<Fr>,
+    prev_pk: PublicKey<EdwardsProjective>,
+    pk: PublicKey<EdwardsProjective>,
+) -> Result<Fr, EthDosError> {
+    let (prev_x, prev_y) = prev_pk
+        .xy()
+        .ok_or_else(|| EthDosError::Signature("public key is the point at infinity".to_string()))?;
+    let (pk_x, pk_y) = pk
+        .xy()
+        .ok_or_else(|| EthDosError::Signature("public key is the point at infinity".to_string()))?;
+    let mut poseidon = PoseidonSponge::new(poseidon_config);
+    poseidon.absorb(&vec![
+        Fr::from(REVOKED_VOUCH_TAG),
+        prev_x,
+        prev_y,
+        pk_x,
+        pk_y,
+    ]);
+    Ok(poseidon.squeeze_field_elements::<Fr>(1)[0])
+}
+
+pub(crate) fn vouch_key_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    poseidon_config: &PoseidonConfig<F>,
+    prev_xy: (FpVar<F>, FpVar<F>),
+    pk_xy: (FpVar<F>, FpVar<F>),
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut poseidon = PoseidonSpongeVar::new(cs, poseidon_config);
+    poseidon.absorb(&vec![
+        FpVar::<F>::constant(F::from(REVOKED_VOUCH_TAG)),
+        prev_xy.0,
+        prev_xy.1,
+        pk_xy.0,
+        pk_xy.1,
+    ])?;
+    let h = poseidon.squeeze_field_elements(1)?;
+    h.first().cloned().ok_or(SynthesisError::Unsatisfiable)
+}
+
+/// Proof that a key is not in the revocation tree: the low leaf of the key, of index `index`, and
+/// its Merkle siblings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NonMembershipProof<F: PrimeField> {
+    pub low_key: F,
+    pub low_next: F,
+    pub index: u32,
+    pub siblings: Vec<F>,
+}
+impl<F: PrimeField> Default for NonMembershipProof<F> {
+    fn default() -> Self {
+        Self {
+            low_key: F::zero(),
+            low_next: F::zero(),
+            index: 0,
+            siblings: vec![F::zero(); REVOCATION_DEPTH],
+        }
+    }
+}
+
+/// Non-membership proofs of the signer public key and of its vouch, the revocation related
+/// external inputs of a vouch step.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NonRevocation<F: PrimeField> {
+    pub pk: NonMembershipProof<F>,
+    pub vouch: NonMembershipProof<F>,
+}
+
+#[derive(Clone, Debug)]
+pub struct NonMembershipProofVar<F: PrimeField> {
+    pub low_key: FpVar<F>,
+    pub low_next: FpVar<F>,
+    pub index_bits: Vec<Boolean<F>>,
+    pub siblings: Vec<FpVar<F>>,
+}
+impl<F: PrimeField> Default for NonMembershipProofVar<F> {
+    fn default() -> Self {
+        Self {
+            low_key: FpVar::<F>::zero(),
+            low_next: FpVar::<F>::zero(),
+            index_bits: vec![Boolean::<F>::FALSE; REVOCATION_DEPTH],
+            siblings: vec![FpVar::<F>::zero(); REVOCATION_DEPTH],
+        }
+    }
+}
+
+impl<F: PrimeField> AllocVar<NonMembershipProof<F>, F> for NonMembershipProofVar<F> {
+    fn new_variable<T: Borrow<NonMembershipProof<F>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        f().and_then(|val| {
+            let cs = cs.into();
+
+            let p = val.borrow();
+            if p.siblings.len() != REVOCATION_DEPTH {
+                return Err(SynthesisError::AssignmentMissing);
+            }
+            let low_key = FpVar::<F>::new_variable(cs.clone(), || Ok(p.low_key), mode)?;
+            let low_next = FpVar::<F>::new_variable(cs.clone(), || Ok(p.low_next), mode)?;
+            let index_bits =
+                Vec::<Boolean<F>>::new_variable(cs.clone(), || Ok(index_bits(p.index)), mode)?;
+            let siblings =
+                Vec::<FpVar<F>>::new_variable(cs.clone(), || Ok(p.siblings.clone()), mode)?;
+            Ok(Self {
+                low_key,
+                low_next,
+                index_bits,
+                siblings,
+            })
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NonRevocationVar<F: PrimeField> {
+    pub pk: NonMembershipProofVar<F>,
+    pub vouch: NonMembershipProofVar<F>,
+}
+
+impl<F: PrimeField> AllocVar<NonRevocation<F>, F> for NonRevocationVar<F> {
+    fn new_variable<T: Borrow<NonRevocation<F>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        f().and_then(|val| {
+            let cs = cs.into();
+
+            let e = val.borrow();
+            let pk = NonMembershipProofVar::new_variable(cs.clone(), || Ok(e.pk.clone()), mode)?;
+            let vouch =
+                NonMembershipProofVar::new_variable(cs.clone(), || Ok(e.vouch.clone()), mode)?;
+            Ok(Self { pk, vouch })
+        })
+    }
+}
+
+// bits of the index of a leaf, from the leaf to the root
+fn index_bits(index: u32) -> Vec<bool> {
+    (0..REVOCATION_DEPTH)
+        .map(|i| (index >> i) & 1 == 1)
+        .collect()
+}
+
+// returns whether `a < b`, given their canonical little endian bits
+fn is_less_than<F: PrimeField>(
+    a: &[Boolean<F>],
+    b: &[Boolean<F>],
+) -> Result<Boolean<F>, SynthesisError> {
+    // from the least significant bit, whether `a < b` when only looking at the bits so far: the
+    // most significant bit in which they differ is the one of `b`
+    let mut lt = Boolean::<F>::FALSE;
+    for (a_i, b_i) in a.iter().zip(b.iter()) {
+        lt = (a_i ^ b_i).select(b_i, &lt)?;
+    }
+    Ok(lt)
+}
+
+/// Enforces, when `enabled` is true, that `key` is not in the revocation tree of the given `root`.
+pub(crate) fn enforce_non_membership<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    poseidon_config: &PoseidonConfig<F>,
+    root: &FpVar<F>,
+    key: &FpVar<F>,
+    proof: &NonMembershipProofVar<F>,
+    enabled: &Boolean<F>,
+) -> Result<(), SynthesisError> {
+    if proof.index_bits.len() != REVOCATION_DEPTH || proof.siblings.len() != REVOCATION_DEPTH {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
+    // the low leaf is in the tree
+    let mut node = hash_two_var(
+        cs.clone(),
+        poseidon_config,
+        proof.low_key.clone(),
+        proof.low_next.clone(),
+    )?;
+    for (sibling, bit) in proof.siblings.iter().zip(proof.index_bits.iter()) {
+        let l = bit.select(sibling, &node)?;
+        let r = bit.select(&node, sibling)?;
+        node = hash_two_var(cs.clone(), poseidon_config, l, r)?;
+    }
+    node.conditional_enforce_equal(root, enabled)?;
+
+    // and the key is between the key of the low leaf and the next one, where the next key zero is
+    // the end of the list. The comparisons are over the canonical bits of the keys.
+    let key_bits = key.to_bits_le()?;
+    let low_key_bits = proof.low_key.to_bits_le()?;
+    let low_next_bits = proof.low_next.to_bits_le()?;
+    let after_low = is_less_than(&low_key_bits, &key_bits)?;
+    let before_next = is_less_than(&key_bits, &low_next_bits)? | proof.low_next.is_zero()?;
+    (after_low & before_next).conditional_enforce_equal(&Boolean::TRUE, enabled)?;
+    Ok(())
+}
+
+/// Indexed Merkle tree of the revoked public keys and vouches. The empty leaves are zero.
+#[derive(Clone, Debug)]
+pub struct RevocationTree {
+    poseidon_config: PoseidonConfig<Fr>,
+    // revoked keys in order of revocation, the key of index `i` being at the leaf `i + 1`
+    keys: Vec<Fr>,
+    // leaf of each revoked key, sorted by key to find the low leaf of a key
+    leaves: BTreeMap<Fr, u32>,
+    // non-empty nodes of each level, from the leaves to the root, indexed by their position
+    layers: Vec<HashMap<u64, Fr>>,
+    // hash of an empty subtree at each level
+    empty: Vec<Fr>,
+}
+
+impl RevocationTree {
+    pub fn new(poseidon_config: &PoseidonConfig<Fr>) -> Self {
+        let mut empty = vec![Fr::zero()];
+        for level in 0..REVOCATION_DEPTH {
+            empty.push(hash_two(poseidon_config, empty[level], empty[level]));
+        }
+        let mut tree = Self {
+            poseidon_config: poseidon_config.clone(),
+            keys: vec![],
+            leaves: BTreeMap::new(),
+            layers: vec![HashMap::new(); REVOCATION_DEPTH + 1],
+            empty,
+        };
+        // the leaf of the zero key, the start of the list
+        tree.set_leaf(0, Fr::zero(), Fr::zero());
+        tree
+    }
+
+    /// Builds the tree from the given revocation keys, in order of revocation
+    pub fn from_keys(
+        poseidon_config: &PoseidonConfig<Fr>,
+        keys: &[Fr],
+    ) -> Result<Self, EthDosError> {
+        let mut tree = Self::new(poseidon_config);
+        for key in keys {
+            tree.insert(*key)?;
+        }
+        Ok(tree)
+    }
+
+    /// Root of the tree with no revoked values
+    pub fn empty_root(poseidon_config: &PoseidonConfig<Fr>) -> Fr {
+        Self::new(poseidon_config).root()
+    }
+
+    pub fn root(&self) -> Fr {
+        self.node(REVOCATION_DEPTH, 0)
+    }
+
+    /// Revoked keys, in order of revocation, which determines the root
+    pub fn keys(&self) -> Vec<Fr> {
+        self.keys.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn is_revoked(&self, key: Fr) -> bool {
+        self.leaves.contains_key(&key)
+    }
+
+    /// Revokes the public key, invalidating all the vouches that it signed
+    pub fn revoke_pk(&mut self, pk: PublicKey<EdwardsProjective>) -> Result<(), EthDosError> {
+        let key = pk_key(&self.poseidon_config, pk);
+        self.insert(key)
+    }
+
+    /// Revokes the vouch of `pk` for `prev_pk`
+    pub fn revoke_vouch(
+        &mut self,
+        prev_pk: PublicKey<EdwardsProjective>,
+        pk: PublicKey<EdwardsProjective>,
+    ) -> Result<(), EthDosError> {
+        let key = vouch_key(&self.poseidon_config, prev_pk, pk)?;
+        self.insert(key)
+    }
+
+    /// Inserts the revocation key in a new leaf, updating its low leaf to point to it
+    pub fn insert(&mut self, key: Fr) -> Result<(), EthDosError> {
+        if key.is_zero() {
+            return Err(EthDosError::Revocation(
+                "the zero key is the start of the list".to_string(),
+            ));
+        }
+        if self.is_revoked(key) {
+            return Ok(());
+        }
+        let index = u32::try_from(self.keys.len() + 1)
+            .map_err(|_| EthDosError::Revocation("the revocation tree is full".to_string()))?;
+
+        let (low_key, low_index, low_next) = self.low_leaf(key);
+        self.set_leaf(low_index, low_key, key);
+        self.set_leaf(index, key, low_next);
+        self.keys.push(key);
+        self.leaves.insert(key, index);
+        Ok(())
+    }
+
+    /// Returns the proof that the given key is not revoked
+    pub fn non_membership(&self, key: Fr) -> Result<NonMembershipProof<Fr>, EthDosError> {
+        if key.is_zero() || self.is_revoked(key) {
+            return Err(EthDosError::Revocation("the key is revoked".to_string()));
+        }
+        let (low_key, index, low_next) = self.low_leaf(key);
+        let siblings = (0..REVOCATION_DEPTH)
+            .map(|level| self.node(level, (u64::from(index) >> level) ^ 1))
+            .collect();
+        Ok(NonMembershipProof {
+            low_key,
+            low_next,
+            index,
+            siblings,
+        })
+    }
+
+    /// Returns the non-revocation proofs of the vouch step folding the signature of `pk` over
+    /// `prev_pk`, failing if the public key or the vouch are revoked.
+    pub fn non_revocation(
+        &self,
+        prev_pk: PublicKey<EdwardsProjective>,
+        pk: PublicKey<EdwardsProjective>,
+    ) -> Result<NonRevocation<Fr>, EthDosError> {
+        let pk_proof = self
+            .non_membership(pk_key(&self.poseidon_config, pk))
+            .map_err(|_| EthDosError::Revocation("the public key is revoked".to_string()))?;
+        let vouch_proof = self
+            .non_membership(vouch_key(&self.poseidon_config, prev_pk, pk)?)
+            .map_err(|_| EthDosError::Revocation("the vouch is revoked".to_string()))?;
+        Ok(NonRevocation {
+            pk: pk_proof,
+            vouch: vouch_proof,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EthDosError> {
+        let mut b = vec![];
+        self.keys.serialize_compressed(&mut b)?;
+        Ok(b)
+    }
+
+    pub fn from_bytes(poseidon_config: &PoseidonConfig<Fr>, b: &[u8]) -> Result<Self, EthDosError> {
+        let keys = Vec::<Fr>::deserialize_compressed(b)?;
+        Self::from_keys(poseidon_config, &keys)
+    }
+
+    // returns the key, index and next key of the low leaf of the given (not revoked) key
+    fn low_leaf(&self, key: Fr) -> (Fr, u32, Fr) {
+        let (low_key, index) = self
+            .leaves
+            .range(..key)
+            .next_back()
+            .map_or((Fr::zero(), 0), |(k, i)| (*k, *i));
+        let low_next = self
+            .leaves
+            .range(key..)
+            .next()
+            .map_or(Fr::zero(), |(k, _)| *k);
+        (low_key, index, low_next)
+    }
+
+    // sets the leaf of the given index, updating the path from it to the root
+    fn set_leaf(&mut self, index: u32, key: Fr, next: Fr) {
+        let mut position = u64::from(index);
+        let mut node = hash_two(&self.poseidon_config, key, next);
+        self.layers[0].insert(position, node);
+        for level in 0..REVOCATION_DEPTH {
+            let sibling = self.node(level, position ^ 1);
+            node = if position & 1 == 0 {
+                hash_two(&self.poseidon_config, node, sibling)
+            } else {
+                hash_two(&self.poseidon_config, sibling, node)
+            };
+            position >>= 1;
+            self.layers[level + 1].insert(position, node);
+        }
+    }
+
+    fn node(&self, level: usize, position: u64) -> Fr {
+        *self.layers[level]
+            .get(&position)
+            .unwrap_or(&self.empty[level])
+    }
+}
+
+impl NonMembershipProof<Fr> {
+    /// Checks natively the non-membership of `key` in the tree of the given `root`, the same check
+    /// done in-circuit by the folding step.
+    pub fn verify(&self, poseidon_config: &PoseidonConfig<Fr>, root: Fr, key: Fr) -> bool {
+        let mut node = hash_two(poseidon_config, self.low_key, self.low_next);
+        for (sibling, bit) in self.siblings.iter().zip(index_bits(self.index)) {
+            node = if bit {
+                hash_two(poseidon_config, *sibling, node)
+            } else {
+                hash_two(poseidon_config, node, *sibling)
+            };
+        }
+        self.siblings.len() == REVOCATION_DEPTH
+            && node == root
+            && self.low_key < key
+            && (key < self.low_next || self.low_next.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_r1cs_std::R1CSVar;
+    use ark_relations::r1cs::ConstraintSystem;
+    use rand::rngs::OsRng;
+
+    use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+
+    use super::*;
+    use crate::keys;
+
+    #[test]
+    fn test_revocation_tree() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let (_, pk_0) = keys::keygen(&mut OsRng).unwrap();
+        let (_, pk_1) = keys::keygen(&mut OsRng).unwrap();
+        let (_, pk_2) = keys::keygen(&mut OsRng).unwrap();
+
+        let mut tree = RevocationTree::new(&poseidon_config);
+        assert_eq!(tree.root(), RevocationTree::empty_root(&poseidon_config));
+        let empty_proof = tree.non_revocation(pk_0, pk_1).unwrap();
+        assert!(empty_proof.pk.verify(
+            &poseidon_config,
+            tree.root(),
+            pk_key(&poseidon_config, pk_1)
+        ));
+
+        tree.revoke_pk(pk_2).unwrap();
+        tree.revoke_vouch(pk_0, pk_1).unwrap();
+        assert_eq!(tree.len(), 2);
+        // revoking twice is a no-op
+        tree.revoke_pk(pk_2).unwrap();
+        assert_eq!(tree.len(), 2);
+
+        // the revoked keys and vouches have no non-membership proof
+        assert!(matches!(
+            tree.non_revocation(pk_0, pk_2),
+            Err(EthDosError::Revocation(_))
+        ));
+        assert!(matches!(
+            tree.non_revocation(pk_0, pk_1),
+            Err(EthDosError::Revocation(_))
+        ));
+        // pk_1 is not revoked, only its vouch for pk_0
+        let proof = tree.non_revocation(pk_2, pk_1).unwrap();
+        let key = pk_key(&poseidon_config, pk_1);
+        assert!(proof.pk.verify(&poseidon_config, tree.root(), key));
+        // a proof for a previous root is not valid for the current one
+        assert!(!empty_proof.pk.verify(&poseidon_config, tree.root(), key));
+
+        // the low leaf of a key larger than all the revoked ones has no next key
+        let keys = tree.keys();
+        let largest = *keys.iter().max().unwrap();
+        let proof = tree.non_membership(largest + Fr::from(1_u64)).unwrap();
+        assert_eq!((proof.low_key, proof.low_next), (largest, Fr::zero()));
+        assert!(proof.verify(&poseidon_config, tree.root(), largest + Fr::from(1_u64)));
+        // keys sharing their low bits have their own leaves, so both can be revoked
+        let key = pk_key(&poseidon_config, pk_0);
+        let colliding = key + Fr::from(1_u64 << 32);
+        tree.insert(key).unwrap();
+        tree.insert(colliding).unwrap();
+        assert!(tree.is_revoked(key) && tree.is_revoked(colliding));
+        assert!(tree.non_membership(colliding).is_err());
+        assert!(tree.insert(Fr::zero()).is_err());
+        assert!(tree.non_membership(Fr::zero()).is_err());
+
+        // serialization roundtrip, the keys being in order of revocation
+        let b = tree.to_bytes().unwrap();
+        let tree2 = RevocationTree::from_bytes(&poseidon_config, &b).unwrap();
+        assert_eq!(tree2.root(), tree.root());
+        assert_eq!(tree2.keys(), tree.keys());
+        assert_eq!(tree.keys()[2..], [key, colliding]);
+    }
+
+    #[test]
+    fn test_non_membership_gadget() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let (_, pk_0) = keys::keygen(&mut OsRng).unwrap();
+        let (_, pk_1) = keys::keygen(&mut OsRng).unwrap();
+        let mut tree = RevocationTree::new(&poseidon_config);
+        tree.revoke_pk(pk_0).unwrap();
+
+        let not_revoked = pk_key(&poseidon_config, pk_1);
+        let revoked = pk_key(&poseidon_config, pk_0);
+        // a 'proof' for the revoked key, with the low leaf of the next key, which is in the tree
+        // but is the leaf of the revoked key itself
+        let forged = tree.non_membership(revoked + Fr::from(1_u64)).unwrap();
+        assert_eq!(forged.low_key, revoked);
+        assert!(!forged.verify(&poseidon_config, tree.root(), revoked));
+        // and one with the low leaf before the revocation, which is no longer in the tree
+        let stale = RevocationTree::new(&poseidon_config)
+            .non_membership(revoked)
+            .unwrap();
+        assert!(!stale.verify(&poseidon_config, tree.root(), revoked));
+
+        for (key, proof, enabled, satisfied) in [
+            (
+                not_revoked,
+                tree.non_membership(not_revoked).unwrap(),
+                true,
+                true,
+            ),
+            (revoked, forged.clone(), true, false),
+            (revoked, forged, false, true),
+            (revoked, stale, true, false),
+        ] {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let root = FpVar::new_witness(cs.clone(), || Ok(tree.root())).unwrap();
+            let key = FpVar::new_witness(cs.clone(), || Ok(key)).unwrap();
+            let proof = NonMembershipProofVar::new_witness(cs.clone(), || Ok(proof)).unwrap();
+            let enabled = Boolean::new_witness(cs.clone(), || Ok(enabled)).unwrap();
+            enforce_non_membership(cs.clone(), &poseidon_config, &root, &key, &proof, &enabled)
+                .unwrap();
+            assert_eq!(root.value().unwrap(), tree.root());
+            assert_eq!(cs.is_satisfied().unwrap(), satisfied);
+        }
+    }
+}
@@ -8,44 +8,203 @@ use ark_ec::{AffineRepr, CurveGroup};
 use ark_ff::{BigInteger, PrimeField};
 use ark_r1cs_std::alloc::{AllocVar, AllocationMode};
 use ark_r1cs_std::boolean::Boolean;
+use ark_r1cs_std::eq::EqGadget;
+use ark_r1cs_std::fields::{fp::FpVar, FieldVar};
 use ark_r1cs_std::prelude::CurveVar;
 use ark_relations::r1cs::{Namespace, SynthesisError};
 use ark_serialize::CanonicalSerialize;
 use ark_std::{rand::Rng, Zero};
+use blake2::{Blake2s256, Digest};
 use core::borrow::Borrow;
 use rand_core::CryptoRngCore;
+use std::convert::TryInto;
 use std::fmt::Debug;
 
 use arkeddsa::{signature::Signature, PublicKey, SigningKey};
 
+use crate::error::EthDosError;
 use crate::fcircuit::CF;
 
+/// `not_after` of the vouches that do not expire
+pub const NO_EXPIRY: u64 = u64::MAX;
+
+/// Validity window of a vouch, in seconds since the unix epoch: the vouch is valid from
+/// `not_before` (inclusive) until `not_after` (exclusive).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Validity {
+    pub not_before: u64,
+    pub not_after: u64,
+}
+impl Default for Validity {
+    fn default() -> Self {
+        Self {
+            not_before: 0,
+            not_after: NO_EXPIRY,
+        }
+    }
+}
+impl Validity {
+    pub fn new(not_before: u64, not_after: u64) -> Result<Self, EthDosError> {
+        if not_before >= not_after {
+            return Err(EthDosError::Signature(format!(
+                "empty validity window, not_before {} is not before not_after {}",
+                not_before, not_after
+            )));
+        }
+        Ok(Self {
+            not_before,
+            not_after,
+        })
+    }
+    /// Returns whether `now` is within the window
+    pub fn contains(&self, now: u64) -> bool {
+        self.not_before <= now && now < self.not_after
+    }
+    /// Window in which both `self` and `other` are valid, ie. the one tracked by the circuit along
+    /// the chain of vouches.
+    pub fn intersect(&self, other: &Self) -> Self {
+        Self {
+            not_before: self.not_before.max(other.not_before),
+            not_after: self.not_after.min(other.not_after),
+        }
+    }
+}
+
+/// Number of edge labels, the label of a vouch is an index in `0..N_LABELS`
+pub const N_LABELS: u8 = 64;
+/// Label of the vouches without attributes
+pub const NO_LABEL: u8 = 0;
+/// Weight of the vouches without attributes
+pub const DEFAULT_WEIGHT: u32 = 1;
+
+/// Attributes of a vouch: a `label` giving a meaning to the edge (eg. "met in person" or
+/// "colleague", the meaning of each label index is defined by the application) and a trust
+/// `weight`. The circuit accumulates the weights of the chain, and the set of its labels as a
+/// bitmask in which the bit `label` is set.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attributes {
+    pub label: u8,
+    pub weight: u32,
+}
+impl Default for Attributes {
+    fn default() -> Self {
+        Self {
+            label: NO_LABEL,
+            weight: DEFAULT_WEIGHT,
+        }
+    }
+}
+impl Attributes {
+    pub fn new(label: u8, weight: u32) -> Result<Self, EthDosError> {
+        if label >= N_LABELS {
+            return Err(EthDosError::Signature(format!(
+                "label {} out of range, the labels go from 0 to {}",
+                label,
+                N_LABELS - 1
+            )));
+        }
+        Ok(Self { label, weight })
+    }
+}
+
+/// Bitmask of the given labels, as in the state of the IVC proofs
+pub fn labels_mask(labels: &[u8]) -> Result<u64, EthDosError> {
+    labels.iter().try_fold(0u64, |mask, label| {
+        Ok(mask | (1u64 << Attributes::new(*label, DEFAULT_WEIGHT)?.label))
+    })
+}
+
 // recall, here C = ed_on_bn254, so C::BaseField = BN254::ScalarField
+//
+// `validity` is the optional validity window of a vouch, and `attributes` its optional label and
+// weight, which are signed together with the previous public key. They are `None` for the plain
+// vouches and for the signatures over challenges.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct SigPk<C: CurveGroup> {
     pub pk: PublicKey<C>,
     pub sig: Signature<C>,
+    pub validity: Option<Validity>,
+    pub attributes: Option<Attributes>,
 }
 impl<C: CurveGroup> Default for SigPk<C> {
     fn default() -> Self {
         Self {
             pk: PublicKey(C::zero().into_affine()),
             sig: Signature::new(C::zero().into_affine(), C::ScalarField::zero()),
+            validity: None,
+            attributes: None,
         }
     }
 }
 impl<C: CurveGroup> SigPk<C> {
+    /// Serializes as `sig || pk`, followed by `not_before || not_after` (little endian) when the
+    /// vouch has a validity window, and by `label || weight` (little endian) when it has
+    /// attributes.
     pub fn to_bytes(self) -> Vec<u8> {
         let sig_bytes = self.sig.to_bytes();
         let pk_bytes = self.pk.to_bytes();
-        [sig_bytes, pk_bytes].concat()
+        let validity_bytes = match self.validity {
+            Some(v) => [v.not_before.to_le_bytes(), v.not_after.to_le_bytes()].concat(),
+            None => vec![],
+        };
+        let attributes_bytes = match self.attributes {
+            Some(a) => [vec![a.label], a.weight.to_le_bytes().to_vec()].concat(),
+            None => vec![],
+        };
+        [sig_bytes, pk_bytes, validity_bytes, attributes_bytes].concat()
     }
-    pub fn from_bytes(b: Vec<u8>) -> Self {
+    pub fn from_bytes(b: Vec<u8>) -> Result<Self, EthDosError> {
         let u_point_size = C::Affine::generator().serialized_size(ark_serialize::Compress::No);
+        let pk_size = PublicKey::<C>(C::Affine::generator()).to_bytes().len();
+        let sig_pk_size = 32 + u_point_size + pk_size;
+        // the lengths of the optional parts are distinct, so their presence is given by the total
+        // length
+        let (has_validity, has_attributes) = match b.len().checked_sub(sig_pk_size) {
+            Some(0) => (false, false),
+            Some(16) => (true, false),
+            Some(5) => (false, true),
+            Some(21) => (true, true),
+            _ => {
+                return Err(EthDosError::Deserialization(format!(
+                    "expected {} bytes for the signature and public key (plus 16 with a validity \
+                     window, and 5 with attributes), got {}",
+                    sig_pk_size,
+                    b.len()
+                )))
+            }
+        };
 
-        let sig = Signature::<C>::from_bytes(&b[..32 + u_point_size]).unwrap();
-        let pk = PublicKey::<C>::from_bytes(&b[32 + u_point_size..]).unwrap();
-        Self { pk, sig }
+        let sig = Signature::<C>::from_bytes(&b[..32 + u_point_size])
+            .map_err(|_| EthDosError::Deserialization("invalid signature bytes".to_string()))?;
+        let pk = pk_from_bytes::<C>(&b[32 + u_point_size..sig_pk_size])?;
+        let u64_at = |i: usize| -> Result<u64, EthDosError> {
+            Ok(u64::from_le_bytes(b[i..i + 8].try_into().map_err(
+                |_| EthDosError::Deserialization("invalid validity bytes".to_string()),
+            )?))
+        };
+        let validity = if has_validity {
+            Some(Validity::new(
+                u64_at(sig_pk_size)?,
+                u64_at(sig_pk_size + 8)?,
+            )?)
+        } else {
+            None
+        };
+        let attributes = if has_attributes {
+            let i = b.len() - 5;
+            let weight = u32::from_le_bytes(b[i + 1..].try_into().map_err(|_| {
+                EthDosError::Deserialization("invalid attributes bytes".to_string())
+            })?);
+            Some(Attributes::new(b[i], weight)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            pk,
+            sig,
+            validity,
+            attributes,
+        })
     }
 }
 
@@ -54,6 +213,12 @@ pub struct SigPkVar<C: CurveGroup, GC: CurveVar<C, CF<C>>> {
     pub pk: GC,
     pub sig_r: GC,
     pub sig_s: Vec<Boolean<CF<C>>>,
+    pub has_validity: Boolean<CF<C>>,
+    pub not_before: FpVar<CF<C>>,
+    pub not_after: FpVar<CF<C>>,
+    pub has_attributes: Boolean<CF<C>>,
+    pub label: FpVar<CF<C>>,
+    pub weight: FpVar<CF<C>>,
 }
 impl<C: CurveGroup, GC: CurveVar<C, CF<C>>> Default for SigPkVar<C, GC> {
     fn default() -> Self {
@@ -61,6 +226,12 @@ impl<C: CurveGroup, GC: CurveVar<C, CF<C>>> Default for SigPkVar<C, GC> {
             pk: GC::zero(),
             sig_r: GC::zero(),
             sig_s: vec![Boolean::<CF<C>>::FALSE; 253], // TODO 253-> fieldbitsize
+            has_validity: Boolean::<CF<C>>::FALSE,
+            not_before: FpVar::<CF<C>>::zero(),
+            not_after: FpVar::<CF<C>>::constant(CF::<C>::from(NO_EXPIRY)),
+            has_attributes: Boolean::<CF<C>>::FALSE,
+            label: FpVar::<CF<C>>::zero(),
+            weight: FpVar::<CF<C>>::constant(CF::<C>::from(DEFAULT_WEIGHT)),
         }
     }
 }
@@ -86,44 +257,370 @@ where
                 || Ok(e.sig.s.into_bigint().to_bits_le()),
                 mode,
             )?;
-            let v = Self { pk, sig_r, sig_s };
+            let validity = e.validity.unwrap_or_default();
+            let has_validity =
+                Boolean::new_variable(cs.clone(), || Ok(e.validity.is_some()), mode)?;
+            let not_before =
+                FpVar::new_variable(cs.clone(), || Ok(CF::<C>::from(validity.not_before)), mode)?;
+            let not_after =
+                FpVar::new_variable(cs.clone(), || Ok(CF::<C>::from(validity.not_after)), mode)?;
+            let attributes = e.attributes.unwrap_or_default();
+            let has_attributes =
+                Boolean::new_variable(cs.clone(), || Ok(e.attributes.is_some()), mode)?;
+            let label =
+                FpVar::new_variable(cs.clone(), || Ok(CF::<C>::from(attributes.label)), mode)?;
+            let weight =
+                FpVar::new_variable(cs.clone(), || Ok(CF::<C>::from(attributes.weight)), mode)?;
+            let v = Self {
+                pk,
+                sig_r,
+                sig_s,
+                has_validity,
+                not_before,
+                not_after,
+                has_attributes,
+                label,
+                weight,
+            };
 
             Ok(v)
         })
     }
 }
 
+/// log2 of the cofactor of the curve of the public keys (ed_on_bn254, whose cofactor is 8)
+const COFACTOR_LOG2: usize = 3;
+
+// returns whether the point is of small order, including the point at infinity. For such public
+// keys the signature equation can be satisfied without knowing the secret key
+fn is_small_order<C: CurveGroup>(p: &C::Affine) -> bool {
+    p.mul_by_cofactor().is_zero()
+}
+
+/// Enforces in-circuit, when `should_enforce`, that the point is not of small order (nor the point
+/// at infinity), as `is_small_order` natively. Used for the public keys and the `R` of the
+/// signatures.
+pub fn enforce_not_small_order<C: CurveGroup, GC: CurveVar<C, CF<C>>>(
+    p: &GC,
+    should_enforce: &Boolean<CF<C>>,
+) -> Result<(), SynthesisError> {
+    let mut q = p.clone();
+    for _ in 0..COFACTOR_LOG2 {
+        q = q.double()?;
+    }
+    q.is_zero()?
+        .conditional_enforce_equal(&Boolean::FALSE, should_enforce)
+}
+
+/// Version of the derivation of the domain tags in `domain_tag`
+pub const DOMAIN_VERSION: u8 = 1;
+/// Context of the default domain
+pub const DEFAULT_CONTEXT: &str = "ethdos";
+
+/// Domain tag of the vouches of the given context (eg. the name of an app or community), which is
+/// absorbed before the public key in the signed messages, so that the signatures of a context can
+/// not be used in another one, nor in other protocols signing Poseidon hashes of public keys.
+pub fn domain_tag<F: PrimeField>(context: &str) -> F {
+    let mut h = Blake2s256::new();
+    h.update(b"ethdos-fold/domain/");
+    h.update([DOMAIN_VERSION]);
+    h.update(context.as_bytes());
+    F::from_le_bytes_mod_order(&h.finalize())
+}
+
+/// Decodes the public key, rejecting the point at infinity, which can not be hashed into the
+/// vouches nor the nullifiers.
+pub fn pk_from_bytes<C: CurveGroup>(b: &[u8]) -> Result<PublicKey<C>, EthDosError> {
+    let pk = PublicKey::<C>::from_bytes(b)
+        .map_err(|_| EthDosError::Deserialization("invalid public key bytes".to_string()))?;
+    pk_xy(pk)?;
+    Ok(pk)
+}
+
+// affine coordinates of the public key, which has none when it is the point at infinity
+fn pk_xy<C: CurveGroup>(pk: PublicKey<C>) -> Result<(C::BaseField, C::BaseField), EthDosError> {
+    pk.xy().ok_or_else(|| {
+        EthDosError::Deserialization("public key is the point at infinity".to_string())
+    })
+}
+
+/// Message signed to vouch for `pk` in the given domain: `Poseidon(domain, pk_x, pk_y)`
 pub fn hash_pk<C: CurveGroup>(
     poseidon_config: &PoseidonConfig<C::BaseField>,
+    domain: C::BaseField,
+    pk: PublicKey<C>,
+) -> Result<C::BaseField, EthDosError>
+where
+    C::BaseField: PrimeField + Absorb,
+{
+    let mut poseidon = PoseidonSponge::new(poseidon_config);
+    let (pk_x, pk_y) = pk_xy(pk)?;
+    poseidon.absorb(&vec![domain, pk_x, pk_y]);
+    Ok(poseidon.squeeze_field_elements::<C::BaseField>(1)[0])
+}
+
+/// Message signed by a vouch with a validity window:
+/// `Poseidon(domain, pk_x, pk_y, not_before, not_after)`
+pub fn hash_pk_validity<C: CurveGroup>(
+    poseidon_config: &PoseidonConfig<C::BaseField>,
+    domain: C::BaseField,
     pk: PublicKey<C>,
-) -> C::BaseField
+    validity: Validity,
+) -> Result<C::BaseField, EthDosError>
 where
     C::BaseField: PrimeField + Absorb,
 {
     let mut poseidon = PoseidonSponge::new(poseidon_config);
-    let (pk_x, pk_y): (C::BaseField, C::BaseField) = pk.xy().unwrap();
-    poseidon.absorb(&vec![pk_x, pk_y]);
-    let k = poseidon.squeeze_field_elements::<C::BaseField>(1);
-    *k.first().unwrap()
+    let (pk_x, pk_y) = pk_xy(pk)?;
+    poseidon.absorb(&vec![
+        domain,
+        pk_x,
+        pk_y,
+        C::BaseField::from(validity.not_before),
+        C::BaseField::from(validity.not_after),
+    ]);
+    Ok(poseidon.squeeze_field_elements::<C::BaseField>(1)[0])
+}
+
+/// Message signed by a vouch with attributes: `Poseidon(msg, label, weight)`, where `msg` is the
+/// message of the vouch without them (`hash_pk` or `hash_pk_validity`)
+pub fn hash_attributes<F: PrimeField + Absorb>(
+    poseidon_config: &PoseidonConfig<F>,
+    msg: F,
+    attributes: Attributes,
+) -> F {
+    let mut poseidon = PoseidonSponge::new(poseidon_config);
+    poseidon.absorb(&vec![
+        msg,
+        F::from(attributes.label),
+        F::from(attributes.weight),
+    ]);
+    poseidon.squeeze_field_elements::<F>(1)[0]
+}
+
+/// Message signed to vouch for `prev_pk`, with or without a validity window and attributes
+pub fn vouch_message<C: CurveGroup>(
+    poseidon_config: &PoseidonConfig<C::BaseField>,
+    domain: C::BaseField,
+    prev_pk: PublicKey<C>,
+    validity: Option<Validity>,
+    attributes: Option<Attributes>,
+) -> Result<C::BaseField, EthDosError>
+where
+    C::BaseField: PrimeField + Absorb,
+{
+    let msg = match validity {
+        Some(validity) => hash_pk_validity(poseidon_config, domain, prev_pk, validity)?,
+        None => hash_pk(poseidon_config, domain, prev_pk)?,
+    };
+    Ok(match attributes {
+        Some(attributes) => hash_attributes(poseidon_config, msg, attributes),
+        None => msg,
+    })
+}
+
+/// Checks natively that `sig_pk.sig` is a valid signature of `sig_pk.pk` over `prev_pk` in the
+/// given domain, ie. the same check done by the folding step.
+pub fn verify_sig_pk<C: CurveGroup>(
+    poseidon_config: &PoseidonConfig<C::BaseField>,
+    domain: C::BaseField,
+    prev_pk: PublicKey<C>,
+    sig_pk: &SigPk<C>,
+) -> Result<(), EthDosError>
+where
+    C::BaseField: PrimeField + Absorb,
+{
+    if prev_pk.xy().is_none() {
+        return Err(EthDosError::Signature(
+            "previous public key is the point at infinity".to_string(),
+        ));
+    }
+    if sig_pk.pk.xy().is_none() {
+        return Err(EthDosError::Signature(
+            "public key is the point at infinity".to_string(),
+        ));
+    }
+    if is_small_order::<C>(&sig_pk.pk.0) || is_small_order::<C>(&sig_pk.sig.r) {
+        return Err(EthDosError::Signature(
+            "public key or signature point of small order".to_string(),
+        ));
+    }
+    if let Some(validity) = sig_pk.validity {
+        Validity::new(validity.not_before, validity.not_after)?;
+    }
+    if let Some(attributes) = sig_pk.attributes {
+        Attributes::new(attributes.label, attributes.weight)?;
+    }
+    let msg = vouch_message(
+        poseidon_config,
+        domain,
+        prev_pk,
+        sig_pk.validity,
+        sig_pk.attributes,
+    )?;
+    sig_pk
+        .pk
+        .verify(poseidon_config, &msg, &sig_pk.sig)
+        .map_err(|_| {
+            EthDosError::Signature("invalid signature over the previous public key".to_string())
+        })
+}
+
+/// Checks natively each link of the chain of signatures before folding it, where the first
+/// signature has to be over `root_pk` (for a chain starting at `pk_0`, the signature of `pk_0` over
+/// itself) and each next one over the public key of the previous. Returns the index of the first
+/// broken link.
+pub fn validate_chain<C: CurveGroup>(
+    poseidon_config: &PoseidonConfig<C::BaseField>,
+    domain: C::BaseField,
+    root_pk: PublicKey<C>,
+    pks_sigs: &[SigPk<C>],
+) -> Result<(), EthDosError>
+where
+    C::BaseField: PrimeField + Absorb,
+{
+    let mut prev_pk = root_pk;
+    for (i, sig_pk) in pks_sigs.iter().enumerate() {
+        verify_sig_pk(poseidon_config, domain, prev_pk, sig_pk).map_err(|e| {
+            EthDosError::Chain {
+                index: i,
+                reason: match e {
+                    EthDosError::Signature(reason) => reason,
+                    e => e.to_string(),
+                },
+            }
+        })?;
+        prev_pk = sig_pk.pk;
+    }
+    Ok(())
+}
+
+/// Domain separator of the message signed in the presentation step, so that it can not collide
+/// with the hash of a public key.
+pub const PRESENTATION_TAG: u64 = 1;
+
+/// Message signed by the last public key of the chain in the presentation step.
+pub fn hash_challenge<F: PrimeField + Absorb>(
+    poseidon_config: &PoseidonConfig<F>,
+    challenge: F,
+) -> F {
+    let mut poseidon = PoseidonSponge::new(poseidon_config);
+    poseidon.absorb(&vec![F::from(PRESENTATION_TAG), challenge]);
+    poseidon.squeeze_field_elements::<F>(1)[0]
+}
+
+/// Nullifier of `pk` for the given challenge, which is the same for each presentation of `pk` with
+/// the same challenge, without revealing `pk`.
+pub fn nullifier<C: CurveGroup>(
+    poseidon_config: &PoseidonConfig<C::BaseField>,
+    pk: PublicKey<C>,
+    challenge: C::BaseField,
+) -> Result<C::BaseField, EthDosError>
+where
+    C::BaseField: PrimeField + Absorb,
+{
+    let mut poseidon = PoseidonSponge::new(poseidon_config);
+    let (pk_x, pk_y) = pk_xy(pk)?;
+    poseidon.absorb(&vec![pk_x, pk_y, challenge]);
+    Ok(poseidon.squeeze_field_elements::<C::BaseField>(1)[0])
+}
+
+/// Signs the verifier's challenge, the external input of the presentation step.
+pub fn sign_challenge<C: CurveGroup>(
+    sk: &SigningKey<C>,
+    poseidon_config: &PoseidonConfig<C::BaseField>,
+    challenge: C::BaseField,
+) -> Result<SigPk<C>, EthDosError>
+where
+    C::BaseField: PrimeField + Absorb,
+{
+    let msg = hash_challenge(poseidon_config, challenge);
+    let sig = sk
+        .sign::<blake2::Blake2b512>(poseidon_config, &msg)
+        .map_err(|_| EthDosError::Signature("could not sign the challenge".to_string()))?;
+    Ok(SigPk {
+        pk: *sk.public_key(),
+        sig,
+        validity: None,
+        attributes: None,
+    })
+}
+
+/// Domain separator of the message signed to prove the ownership of a public key outside of the
+/// circuit, so that it can not be used as the signature of a presentation step nor of a vouch.
+pub const OWNERSHIP_TAG: u64 = 2;
+
+/// Message signed to prove the ownership of a public key for the verifier's challenge.
+pub fn hash_ownership_challenge<F: PrimeField + Absorb>(
+    poseidon_config: &PoseidonConfig<F>,
+    challenge: F,
+) -> F {
+    let mut poseidon = PoseidonSponge::new(poseidon_config);
+    poseidon.absorb(&vec![F::from(OWNERSHIP_TAG), challenge]);
+    poseidon.squeeze_field_elements::<F>(1)[0]
+}
+
+/// Signs the verifier's challenge with the given key, proving its ownership natively.
+pub fn sign_ownership<C: CurveGroup>(
+    sk: &SigningKey<C>,
+    poseidon_config: &PoseidonConfig<C::BaseField>,
+    challenge: C::BaseField,
+) -> Result<SigPk<C>, EthDosError>
+where
+    C::BaseField: PrimeField + Absorb,
+{
+    let msg = hash_ownership_challenge(poseidon_config, challenge);
+    let sig = sk
+        .sign::<blake2::Blake2b512>(poseidon_config, &msg)
+        .map_err(|_| EthDosError::Signature("could not sign the challenge".to_string()))?;
+    Ok(SigPk {
+        pk: *sk.public_key(),
+        sig,
+        validity: None,
+        attributes: None,
+    })
+}
+
+/// Checks natively that `ownership.sig` is a valid signature of `ownership.pk` over the challenge,
+/// as generated by `sign_ownership`.
+pub fn verify_ownership<C: CurveGroup>(
+    poseidon_config: &PoseidonConfig<C::BaseField>,
+    ownership: &SigPk<C>,
+    challenge: C::BaseField,
+) -> Result<(), EthDosError>
+where
+    C::BaseField: PrimeField + Absorb,
+{
+    if ownership.pk.xy().is_none() {
+        return Err(EthDosError::Signature(
+            "public key is the point at infinity".to_string(),
+        ));
+    }
+    let msg = hash_ownership_challenge(poseidon_config, challenge);
+    ownership
+        .pk
+        .verify(poseidon_config, &msg, &ownership.sig)
+        .map_err(|_| EthDosError::Signature("invalid signature over the challenge".to_string()))
 }
 
 // returns a vector of signatures & publickeys, where each signature is of the previous public key
 pub fn gen_signatures<R: Rng + CryptoRngCore, C: CurveGroup>(
     rng: &mut R,
     poseidon_config: &PoseidonConfig<C::BaseField>,
+    domain: C::BaseField,
     steps: usize,
-) -> Vec<SigPk<C>>
+) -> Result<Vec<SigPk<C>>, EthDosError>
 where
     C::BaseField: PrimeField + Absorb,
 {
     let mut prev_pk = None;
     let mut res: Vec<SigPk<C>> = Vec::new();
     for _ in 0..steps {
-        let extinp = gen_sig(rng, poseidon_config, prev_pk);
+        let extinp = gen_sig(rng, poseidon_config, domain, prev_pk)?;
         res.push(extinp);
         prev_pk = Some(extinp.pk);
     }
-    res
+    Ok(res)
 }
 
 // generates a new secret key, and signs the given `prev_pk` with it. If the `prev_pk==None`, it
@@ -131,22 +628,198 @@ where
 pub fn gen_sig<R: Rng + CryptoRngCore, C: CurveGroup>(
     rng: &mut R,
     poseidon_config: &PoseidonConfig<C::BaseField>,
+    domain: C::BaseField,
     prev_pk: Option<PublicKey<C>>,
-) -> SigPk<C>
+) -> Result<SigPk<C>, EthDosError>
 where
     C::BaseField: PrimeField + Absorb,
 {
-    let sk = SigningKey::<C>::generate::<blake2::Blake2b512>(rng).unwrap();
-    let pk = sk.public_key();
+    let sk = SigningKey::<C>::generate::<blake2::Blake2b512>(rng)
+        .map_err(|_| EthDosError::Signature("could not generate the signing key".to_string()))?;
 
     // if prev_pk!=None, use it, else, set the new pk to it
-    let prev_pk = if let Some(v) = prev_pk { v } else { *pk };
+    let prev_pk = if let Some(v) = prev_pk {
+        v
+    } else {
+        *sk.public_key()
+    };
+
+    sign_pk(&sk, poseidon_config, domain, prev_pk)
+}
+
+// signs the given `prev_pk` with the given signing key, ie. the owner of `sk` vouches for
+// `prev_pk` in the given domain. The returned SigPk is the external input of the folding step that
+// goes from `prev_pk` to the public key of `sk`.
+pub fn sign_pk<C: CurveGroup>(
+    sk: &SigningKey<C>,
+    poseidon_config: &PoseidonConfig<C::BaseField>,
+    domain: C::BaseField,
+    prev_pk: PublicKey<C>,
+) -> Result<SigPk<C>, EthDosError>
+where
+    C::BaseField: PrimeField + Absorb,
+{
+    sign_pk_with_validity(sk, poseidon_config, domain, prev_pk, None)
+}
 
-    let msg = hash_pk(poseidon_config, prev_pk);
+// same as `sign_pk`, but the vouch is only valid within the given validity window (when given)
+pub fn sign_pk_with_validity<C: CurveGroup>(
+    sk: &SigningKey<C>,
+    poseidon_config: &PoseidonConfig<C::BaseField>,
+    domain: C::BaseField,
+    prev_pk: PublicKey<C>,
+    validity: Option<Validity>,
+) -> Result<SigPk<C>, EthDosError>
+where
+    C::BaseField: PrimeField + Absorb,
+{
+    sign_vouch(sk, poseidon_config, domain, prev_pk, validity, None)
+}
+
+// same as `sign_pk_with_validity`, but also signing the attributes of the vouch (when given)
+pub fn sign_vouch<C: CurveGroup>(
+    sk: &SigningKey<C>,
+    poseidon_config: &PoseidonConfig<C::BaseField>,
+    domain: C::BaseField,
+    prev_pk: PublicKey<C>,
+    validity: Option<Validity>,
+    attributes: Option<Attributes>,
+) -> Result<SigPk<C>, EthDosError>
+where
+    C::BaseField: PrimeField + Absorb,
+{
+    if let Some(validity) = validity {
+        Validity::new(validity.not_before, validity.not_after)?;
+    }
+    if let Some(attributes) = attributes {
+        Attributes::new(attributes.label, attributes.weight)?;
+    }
+    let pk = sk.public_key();
+    let msg = vouch_message(poseidon_config, domain, prev_pk, validity, attributes)?;
 
     let sig = sk
         .sign::<blake2::Blake2b512>(poseidon_config, &msg)
+        .map_err(|_| EthDosError::Signature("could not sign the message".to_string()))?;
+    pk.verify(poseidon_config, &msg, &sig)
+        .map_err(|_| EthDosError::Signature("invalid signature".to_string()))?;
+    Ok(SigPk {
+        pk: *pk,
+        sig,
+        validity,
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bn254::Fr;
+    use rand::rngs::OsRng;
+
+    use arkeddsa::ed_on_bn254_twist::EdwardsProjective;
+    use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_chain() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let domain = domain_tag::<Fr>(DEFAULT_CONTEXT);
+        let sigs =
+            gen_signatures::<OsRng, EdwardsProjective>(&mut OsRng, &poseidon_config, domain, 4)
+                .unwrap();
+        let pk_0 = sigs[0].pk;
+
+        validate_chain(&poseidon_config, domain, pk_0, &sigs).unwrap();
+        // a chain can also be validated from an intermediate public key (as when extending a proof)
+        validate_chain(&poseidon_config, domain, sigs[1].pk, &sigs[2..]).unwrap();
+
+        // out of order
+        let mut swapped = sigs.clone();
+        swapped.swap(2, 3);
+        assert!(matches!(
+            validate_chain(&poseidon_config, domain, pk_0, &swapped),
+            Err(EthDosError::Chain { index: 2, .. })
+        ));
+
+        // unrelated root
+        assert!(matches!(
+            validate_chain(&poseidon_config, domain, sigs[3].pk, &sigs),
+            Err(EthDosError::Chain { index: 0, .. })
+        ));
+
+        // signature not matching its public key
+        let mut tampered = sigs.clone();
+        tampered[1].sig = sigs[2].sig;
+        assert!(matches!(
+            validate_chain(&poseidon_config, domain, pk_0, &tampered),
+            Err(EthDosError::Chain { index: 1, .. })
+        ));
+
+        // the signatures are bound to their domain
+        assert!(matches!(
+            validate_chain(&poseidon_config, domain_tag("other"), pk_0, &sigs),
+            Err(EthDosError::Chain { index: 0, .. })
+        ));
+
+        // the attributes are signed together with the previous public key
+        let sk =
+            SigningKey::<EdwardsProjective>::generate::<blake2::Blake2b512>(&mut OsRng).unwrap();
+        let attributes = Attributes::new(5, 7).unwrap();
+        let labelled = sign_vouch(
+            &sk,
+            &poseidon_config,
+            domain,
+            sigs[3].pk,
+            None,
+            Some(attributes),
+        )
         .unwrap();
-    pk.verify(poseidon_config, &msg, &sig).unwrap();
-    SigPk { pk: *pk, sig }
+        let chain = [sigs.clone(), vec![labelled]].concat();
+        validate_chain(&poseidon_config, domain, pk_0, &chain).unwrap();
+        assert_eq!(
+            SigPk::<EdwardsProjective>::from_bytes(labelled.to_bytes()).unwrap(),
+            labelled
+        );
+        let mut tampered = chain.clone();
+        tampered[4].attributes = Some(Attributes::new(5, 8).unwrap());
+        assert!(matches!(
+            validate_chain(&poseidon_config, domain, pk_0, &tampered),
+            Err(EthDosError::Chain { index: 4, .. })
+        ));
+        tampered[4].attributes = None;
+        assert!(validate_chain(&poseidon_config, domain, pk_0, &tampered).is_err());
+        assert!(Attributes::new(N_LABELS, 1).is_err());
+        assert_eq!(labels_mask(&[0, 2, 63]).unwrap(), 1 | 1 << 2 | 1 << 63);
+
+        // point at infinity
+        let mut tampered = sigs;
+        tampered[3] = SigPk::default();
+        assert!(matches!(
+            validate_chain(&poseidon_config, domain, pk_0, &tampered),
+            Err(EthDosError::Chain { index: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_identity_pk() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let domain = domain_tag::<Fr>(DEFAULT_CONTEXT);
+        let identity = PublicKey::<EdwardsProjective>(EdwardsProjective::zero().into_affine());
+
+        // the point at infinity is rejected instead of panicking when hashed
+        assert!(hash_pk(&poseidon_config, domain, identity).is_err());
+        assert!(hash_pk_validity(&poseidon_config, domain, identity, Validity::default()).is_err());
+        assert!(nullifier(&poseidon_config, identity, Fr::from(42_u32)).is_err());
+        let sk =
+            SigningKey::<EdwardsProjective>::generate::<blake2::Blake2b512>(&mut OsRng).unwrap();
+        assert!(sign_vouch(&sk, &poseidon_config, domain, identity, None, None).is_err());
+
+        // and when decoded
+        assert!(pk_from_bytes::<EdwardsProjective>(&identity.to_bytes()).is_err());
+        let sig_pk = SigPk::<EdwardsProjective>::default();
+        assert!(matches!(
+            SigPk::<EdwardsProjective>::from_bytes(sig_pk.to_bytes()),
+            Err(EthDosError::Deserialization(_))
+        ));
+    }
 }
@@ -37,11 +37,11 @@ pub fn elapsed(start: u64) -> u64 {
 
 #[cfg(target_arch = "wasm32")]
 fn get_wasm_time() -> u64 {
-    use web_sys::window;
-    let window = window().expect("no window");
-    let performance = window
-        .performance()
-        .expect("window.performance() not found");
+    use wasm_bindgen::JsCast;
+    // the global scope is the window in the main thread, and the worker scope in the web workers
+    let performance = js_sys::Reflect::get(&js_sys::global(), &"performance".into())
+        .expect("performance not found")
+        .unchecked_into::<web_sys::Performance>();
     performance.now() as u64
 }
 
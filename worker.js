@@ -0,0 +1,55 @@
+// Runs the wasm module for index.html. With the threads build (see the README) the folding runs
+// over a pool of web workers, and the thread calling the wasm methods has to be able to block
+// waiting for them, which the main thread of the page can not, so the methods run in this worker.
+// The threads build needs SharedArrayBuffer, only available when the page is cross-origin
+// isolated, otherwise the single-threaded build is used.
+
+// forward the logs to the page
+for (const type of ["log", "info", "warn", "error"]) {
+  const log = console[type];
+  console[type] = (...args) => {
+    log.apply(console, args);
+    self.postMessage({ log: type, message: args.join(" ") });
+  };
+}
+
+async function load() {
+  if (self.crossOriginIsolated) {
+    const wasm = await import("./pkg-threads/ethdos_fold.js");
+    await wasm.default();
+    await wasm.init_thread_pool(navigator.hardwareConcurrency);
+    console.log(`ready, ${navigator.hardwareConcurrency} threads`);
+    return wasm;
+  }
+  const wasm = await import("./pkg/ethdos_fold.js");
+  await wasm.default();
+  console.log("ready, single-threaded (the page is not cross-origin isolated)");
+  return wasm;
+}
+const ready = load();
+
+// flag set by the page to cancel the folding, over a SharedArrayBuffer so that it can be read
+// while this worker is busy folding (only when the page is cross-origin isolated, otherwise the
+// page cancels by terminating the worker)
+let cancel_flag;
+
+self.onmessage = async (e) => {
+  if (e.data.cancel_flag !== undefined) {
+    cancel_flag = e.data.cancel_flag;
+    return;
+  }
+  const { id, fn, args } = e.data;
+  if (fn === "fold_sigs") {
+    // `on_progress`, the folding is cancelled when it returns false
+    args[4] = (step, n_steps, elapsed_ms, eta_ms) => {
+      self.postMessage({ progress: { step, n_steps, elapsed_ms, eta_ms } });
+      return cancel_flag === undefined || Atomics.load(cancel_flag, 0) === 0;
+    };
+  }
+  try {
+    const wasm = await ready;
+    self.postMessage({ id, result: wasm[fn](...args) });
+  } catch (err) {
+    self.postMessage({ id, error: err.toString() });
+  }
+};